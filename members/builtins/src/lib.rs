@@ -8,5 +8,6 @@ extern crate smallvec;
 pub mod calc;
 pub mod conditionals;
 pub mod echo;
+pub mod printf;
 pub mod random;
 pub mod test;
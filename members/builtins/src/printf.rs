@@ -0,0 +1,640 @@
+use small;
+
+/// The flags, width, and precision of a conversion specification.
+#[derive(Default)]
+struct Spec {
+    left:      bool,
+    sign:      bool,
+    space:     bool,
+    alternate: bool,
+    zero:      bool,
+    width:     usize,
+    precision: Option<usize>,
+}
+
+/// The largest width or precision, which is the largest value of an `int` in C.
+const MAX_BOUND: i64 = i32::max_value() as i64;
+
+/// The arguments which are consumed by the conversions of a format.
+struct Arguments<'a> {
+    args:  &'a [small::String],
+    index: usize,
+    error: Option<small::String>,
+}
+
+impl<'a> Arguments<'a> {
+    fn next(&mut self) -> Option<&'a str> {
+        let arg = self.args.get(self.index)?;
+        self.index += 1;
+        Some(arg.as_str())
+    }
+
+    /// Records the first of the errors which were found among the arguments.
+    fn fail(&mut self, arg: &str, why: &str) {
+        if self.error.is_none() {
+            self.error = Some(format!("{}: {}", arg, why).into());
+        }
+    }
+
+    fn signed(&mut self) -> i64 {
+        let arg = match self.next() {
+            Some(arg) => arg,
+            None => return 0,
+        };
+        match parse_integer(arg) {
+            Some(value) if value > i128::from(i64::max_value()) => {
+                self.fail(arg, "value out of range");
+                i64::max_value()
+            }
+            Some(value) if value < i128::from(i64::min_value()) => {
+                self.fail(arg, "value out of range");
+                i64::min_value()
+            }
+            Some(value) => value as i64,
+            None => {
+                self.fail(arg, "expected a numeric value");
+                0
+            }
+        }
+    }
+
+    /// Negative values are converted to unsigned values as they are in C.
+    fn unsigned(&mut self) -> u64 {
+        let arg = match self.next() {
+            Some(arg) => arg,
+            None => return 0,
+        };
+        match parse_integer(arg) {
+            Some(value) if value > i128::from(u64::max_value()) => {
+                self.fail(arg, "value out of range");
+                u64::max_value()
+            }
+            Some(value) if value < i128::from(i64::min_value()) => {
+                self.fail(arg, "value out of range");
+                i64::min_value() as u64
+            }
+            Some(value) => value as u64,
+            None => {
+                self.fail(arg, "expected a numeric value");
+                0
+            }
+        }
+    }
+
+    /// Takes a width or precision from the next argument, which must fit within an `int`, as it
+    /// must in C, so that the output cannot grow without bound.
+    fn bound(&mut self, what: &str) -> Result<i64, small::String> {
+        let arg = self.args.get(self.index).map_or("", |arg| arg.as_str());
+        let value = self.signed();
+        if value > MAX_BOUND || value < -MAX_BOUND {
+            return Err(format!("{}: invalid {}", arg, what).into());
+        }
+        Ok(value)
+    }
+
+    fn float(&mut self) -> f64 {
+        let arg = match self.next() {
+            Some(arg) => arg,
+            None => return 0.0,
+        };
+        let value = arg.trim_start().parse::<f64>().ok();
+        match value.or_else(|| parse_integer(arg).map(|value| value as f64)) {
+            Some(value) => value,
+            None => {
+                self.fail(arg, "expected a numeric value");
+                0.0
+            }
+        }
+    }
+}
+
+/// Parses an integer, which may be given in decimal, in hexadecimal with a leading `0x`, in
+/// octal with a leading `0`, or as the character code of the character which follows a quote.
+fn parse_integer(text: &str) -> Option<i128> {
+    let text = text.trim_start();
+    if text.starts_with('\'') || text.starts_with('"') {
+        return Some(text[1..].chars().next().map_or(0, |character| character as i128));
+    }
+
+    let (negative, text) = match text.as_bytes().get(0) {
+        Some(&b'-') => (true, &text[1..]),
+        Some(&b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let (radix, digits) = if text.starts_with("0x") || text.starts_with("0X") {
+        (16, &text[2..])
+    } else if text.starts_with('0') && text.len() > 1 {
+        (8, &text[1..])
+    } else {
+        (10, text)
+    };
+
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_alphanumeric()) {
+        return None;
+    }
+    let value = i128::from(u64::from_str_radix(digits, radix).ok()?);
+    Some(if negative { -value } else { value })
+}
+
+/// Parses up to `max` hexadecimal digits, returning their value and the number of digits.
+fn hex_digits(bytes: &[u8], max: usize) -> (u32, usize) {
+    let digits = bytes.iter().take(max).take_while(|byte| byte.is_ascii_hexdigit()).count();
+    let value = bytes[..digits]
+        .iter()
+        .fold(0, |value, &byte| value * 16 + (byte as char).to_digit(16).unwrap_or(0));
+    (value, digits)
+}
+
+/// Writes the character which is denoted by the escape sequence at the start of the bytes,
+/// which followed a backslash, returning the number of bytes which the sequence spanned.
+///
+/// Within the arguments of `%b`, octal sequences are introduced with `\0`, and `None` is
+/// returned for `\c`, which ends the output.
+fn escape(bytes: &[u8], output: &mut Vec<u8>, argument: bool) -> Option<usize> {
+    let byte = match bytes.get(0) {
+        Some(&byte) => byte,
+        None => {
+            output.push(b'\\');
+            return Some(0);
+        }
+    };
+
+    let character = match byte {
+        b'\\' | b'"' | b'\'' => byte,
+        b'a' => 7,
+        b'b' => 8,
+        b'e' => 27,
+        b'f' => 12,
+        b'n' => b'\n',
+        b'r' => b'\r',
+        b't' => b'\t',
+        b'v' => 11,
+        b'c' if argument => return None,
+        b'0'..=b'7' => {
+            let skip = if argument && byte == b'0' { 1 } else { 0 };
+            let digits = bytes[skip..]
+                .iter()
+                .take(3)
+                .take_while(|&&digit| digit >= b'0' && digit <= b'7')
+                .count();
+            let value = bytes[skip..skip + digits]
+                .iter()
+                .fold(0u32, |value, &digit| value * 8 + u32::from(digit - b'0'));
+            output.push(value as u8);
+            return Some(skip + digits);
+        }
+        b'x' => {
+            let (value, digits) = hex_digits(&bytes[1..], 2);
+            if digits == 0 {
+                output.extend_from_slice(b"\\x");
+                return Some(1);
+            }
+            output.push(value as u8);
+            return Some(1 + digits);
+        }
+        b'u' | b'U' => {
+            let max = if byte == b'u' { 4 } else { 8 };
+            let (value, digits) = hex_digits(&bytes[1..], max);
+            match std::char::from_u32(value).filter(|_| digits == max) {
+                Some(character) => {
+                    let mut buffer = [0; 4];
+                    output.extend_from_slice(character.encode_utf8(&mut buffer).as_bytes());
+                    return Some(1 + digits);
+                }
+                None => {
+                    output.extend_from_slice(&[b'\\', byte]);
+                    return Some(1);
+                }
+            }
+        }
+        _ => {
+            output.extend_from_slice(&[b'\\', byte]);
+            return Some(1);
+        }
+    };
+
+    output.push(character);
+    Some(1)
+}
+
+/// Writes the body of a conversion, padded to the width of the specification. Padding with
+/// zeroes is placed between the prefix, such as a sign, and the body.
+fn pad(output: &mut Vec<u8>, spec: &Spec, prefix: &str, body: &[u8], zeroes: bool) {
+    let length = prefix.len() + String::from_utf8_lossy(body).chars().count();
+    let fill = spec.width.saturating_sub(length);
+    if spec.left {
+        output.extend_from_slice(prefix.as_bytes());
+        output.extend_from_slice(body);
+        output.extend((0..fill).map(|_| b' '));
+    } else if zeroes {
+        output.extend_from_slice(prefix.as_bytes());
+        output.extend((0..fill).map(|_| b'0'));
+        output.extend_from_slice(body);
+    } else {
+        output.extend((0..fill).map(|_| b' '));
+        output.extend_from_slice(prefix.as_bytes());
+        output.extend_from_slice(body);
+    }
+}
+
+/// The sign of a signed number, according to the flags of the specification.
+fn sign(spec: &Spec, negative: bool) -> &'static str {
+    if negative {
+        "-"
+    } else if spec.sign {
+        "+"
+    } else if spec.space {
+        " "
+    } else {
+        ""
+    }
+}
+
+fn integer(output: &mut Vec<u8>, spec: &Spec, conversion: u8, magnitude: u64, negative: bool) {
+    let mut digits = match conversion {
+        b'o' => format!("{:o}", magnitude),
+        b'x' => format!("{:x}", magnitude),
+        b'X' => format!("{:X}", magnitude),
+        _ => magnitude.to_string(),
+    };
+    if spec.precision == Some(0) && magnitude == 0 {
+        digits.clear();
+    }
+    if let Some(precision) = spec.precision {
+        if precision > digits.len() {
+            digits = format!("{:0>width$}", digits, width = precision);
+        }
+    }
+
+    let prefix = match conversion {
+        b'd' | b'i' => sign(spec, negative),
+        b'o' if spec.alternate && !digits.starts_with('0') => "0",
+        b'x' if spec.alternate && magnitude != 0 => "0x",
+        b'X' if spec.alternate && magnitude != 0 => "0X",
+        _ => "",
+    };
+    let zeroes = spec.zero && !spec.left && spec.precision.is_none();
+    pad(output, spec, prefix, digits.as_bytes(), zeroes);
+}
+
+fn fixed(value: f64, precision: usize, alternate: bool) -> String {
+    let mut text = format!("{:.*}", precision, value);
+    if alternate && precision == 0 {
+        text.push('.');
+    }
+    text
+}
+
+/// Formats a number in scientific notation, with an exponent of at least two digits.
+fn exponential(value: f64, precision: usize, alternate: bool) -> String {
+    let text = format!("{:.*e}", precision, value);
+    let mut parts = text.splitn(2, 'e');
+    let mut mantissa = parts.next().unwrap_or("").to_owned();
+    let exponent = parts.next().and_then(|exponent| exponent.parse::<i32>().ok()).unwrap_or(0);
+    if alternate && precision == 0 {
+        mantissa.push('.');
+    }
+    format!("{}e{}{:02}", mantissa, if exponent < 0 { '-' } else { '+' }, exponent.abs())
+}
+
+/// Formats a number with the given number of significant digits, in scientific notation if its
+/// exponent is very small or large. Trailing zeroes are removed, unless the format is alternate.
+fn general(value: f64, precision: usize, alternate: bool) -> String {
+    let precision = precision.max(1);
+    let exponent = format!("{:.*e}", precision - 1, value)
+        .splitn(2, 'e')
+        .nth(1)
+        .and_then(|exponent| exponent.parse::<i32>().ok())
+        .unwrap_or(0);
+
+    let text = if exponent >= -4 && exponent < precision as i32 {
+        fixed(value, (precision as i32 - 1 - exponent) as usize, alternate)
+    } else {
+        exponential(value, precision - 1, alternate)
+    };
+    if alternate {
+        return text;
+    }
+
+    let split = text.find('e').unwrap_or_else(|| text.len());
+    if text[..split].contains('.') {
+        let (mantissa, exponent) = text.split_at(split);
+        [mantissa.trim_end_matches('0').trim_end_matches('.'), exponent].concat()
+    } else {
+        text
+    }
+}
+
+fn float(output: &mut Vec<u8>, spec: &Spec, conversion: u8, value: f64) {
+    let precision = spec.precision.unwrap_or(6);
+    let magnitude = value.abs();
+    let body = if !value.is_finite() {
+        if value.is_nan() { "nan".to_owned() } else { "inf".to_owned() }
+    } else {
+        match conversion.to_ascii_lowercase() {
+            b'e' => exponential(magnitude, precision, spec.alternate),
+            b'g' => general(magnitude, precision, spec.alternate),
+            _ => fixed(magnitude, precision, spec.alternate),
+        }
+    };
+    let body = if conversion.is_ascii_uppercase() { body.to_uppercase() } else { body };
+
+    let negative = value.is_sign_negative() && !value.is_nan();
+    let zeroes = spec.zero && !spec.left && value.is_finite();
+    pad(output, spec, sign(spec, negative), body.as_bytes(), zeroes);
+}
+
+/// Quotes text so that the shell would read it back as a single argument.
+fn quote(text: &str) -> String {
+    let safe = |character: char| character.is_alphanumeric() || "_-./:,+=".contains(character);
+    if !text.is_empty() && text.chars().all(safe) {
+        text.to_owned()
+    } else {
+        ["'", &text.replace('\'', r"'\''"), "'"].concat()
+    }
+}
+
+/// Truncates text to the precision of the specification, which is a number of characters.
+fn truncate(text: &str, precision: Option<usize>) -> &str {
+    match precision.and_then(|precision| text.char_indices().nth(precision)) {
+        Some((index, _)) => &text[..index],
+        None => text,
+    }
+}
+
+/// Writes the format once, consuming arguments for its conversions. Returns `false` if the
+/// output was ended by a `\c` within an argument of `%b`.
+fn format_once(
+    format: &[u8],
+    arguments: &mut Arguments,
+    output: &mut Vec<u8>,
+) -> Result<bool, small::String> {
+    let mut index = 0;
+    while let Some(&byte) = format.get(index) {
+        index += 1;
+        match byte {
+            b'\\' => index += escape(&format[index..], output, false).unwrap_or(0),
+            b'%' => {
+                let start = index - 1;
+                let mut spec = Spec::default();
+                while let Some(&flag) = format.get(index) {
+                    match flag {
+                        b'-' => spec.left = true,
+                        b'+' => spec.sign = true,
+                        b' ' => spec.space = true,
+                        b'#' => spec.alternate = true,
+                        b'0' => spec.zero = true,
+                        _ => break,
+                    }
+                    index += 1;
+                }
+
+                // A width or precision of `*` is taken from the next argument.
+                let number = |index: &mut usize, what: &str| {
+                    let digits = format[*index..].iter().take_while(|b| b.is_ascii_digit()).count();
+                    *index += digits;
+                    let value = format[*index - digits..*index]
+                        .iter()
+                        .try_fold(0i64, |value, &digit| {
+                            Some(value * 10 + i64::from(digit - b'0')).filter(|&v| v <= MAX_BOUND)
+                        });
+                    match value {
+                        Some(value) => Ok(value as usize),
+                        None => {
+                            let spec = String::from_utf8_lossy(&format[start..*index]);
+                            Err(small::String::from(format!("{}: invalid {}", spec, what)))
+                        }
+                    }
+                };
+                if format.get(index) == Some(&b'*') {
+                    index += 1;
+                    let width = arguments.bound("field width")?;
+                    spec.left |= width < 0;
+                    spec.width = width.abs() as usize;
+                } else {
+                    spec.width = number(&mut index, "field width")?;
+                }
+                if format.get(index) == Some(&b'.') {
+                    index += 1;
+                    spec.precision = if format.get(index) == Some(&b'*') {
+                        index += 1;
+                        let precision = arguments.bound("precision")?;
+                        if precision < 0 {
+                            None
+                        } else {
+                            Some(precision as usize)
+                        }
+                    } else {
+                        Some(number(&mut index, "precision")?)
+                    };
+                }
+
+                let conversion = match format.get(index) {
+                    Some(&conversion) => conversion,
+                    None => {
+                        let spec = String::from_utf8_lossy(&format[start..]);
+                        return Err(format!("{}: missing conversion specifier", spec).into());
+                    }
+                };
+                index += 1;
+
+                match conversion {
+                    b'%' => output.push(b'%'),
+                    b's' => {
+                        let text = arguments.next().unwrap_or("");
+                        pad(output, &spec, "", truncate(text, spec.precision).as_bytes(), false);
+                    }
+                    b'q' => {
+                        let text = quote(arguments.next().unwrap_or(""));
+                        pad(output, &spec, "", truncate(&text, spec.precision).as_bytes(), false);
+                    }
+                    b'c' => {
+                        let text = arguments.next().unwrap_or("");
+                        pad(output, &spec, "", truncate(text, Some(1)).as_bytes(), false);
+                    }
+                    b'b' => {
+                        let text = arguments.next().unwrap_or("").as_bytes();
+                        let mut expanded = Vec::with_capacity(text.len());
+                        let mut ended = false;
+                        let mut position = 0;
+                        while let Some(&byte) = text.get(position) {
+                            position += 1;
+                            if byte != b'\\' {
+                                expanded.push(byte);
+                                continue;
+                            }
+                            match escape(&text[position..], &mut expanded, true) {
+                                Some(length) => position += length,
+                                None => {
+                                    ended = true;
+                                    break;
+                                }
+                            }
+                        }
+
+                        let length = match spec.precision {
+                            Some(precision) => precision.min(expanded.len()),
+                            None => expanded.len(),
+                        };
+                        pad(output, &spec, "", &expanded[..length], false);
+                        if ended {
+                            return Ok(false);
+                        }
+                    }
+                    b'd' | b'i' => {
+                        let value = arguments.signed();
+                        let magnitude = if value < 0 {
+                            (value as u64).wrapping_neg()
+                        } else {
+                            value as u64
+                        };
+                        integer(output, &spec, conversion, magnitude, value < 0);
+                    }
+                    b'u' | b'o' | b'x' | b'X' => {
+                        let value = arguments.unsigned();
+                        integer(output, &spec, conversion, value, false);
+                    }
+                    b'f' | b'F' | b'e' | b'E' | b'g' | b'G' => {
+                        let value = arguments.float();
+                        float(output, &spec, conversion, value);
+                    }
+                    _ => {
+                        let spec = String::from_utf8_lossy(&format[start..index]);
+                        return Err(format!("{}: invalid conversion specification", spec).into());
+                    }
+                }
+            }
+            _ => output.push(byte),
+        }
+    }
+    Ok(true)
+}
+
+/// Formats the arguments according to the format, like the `printf` of C, appending the result
+/// to the output. The format is reused for as long as arguments remain, and conversions which
+/// lack an argument are given an empty string, or zero.
+///
+/// Arguments which are not valid numbers are formatted as zero, and are reported in the error
+/// which is returned once the whole output has been written.
+pub fn printf(
+    format: &str,
+    args: &[small::String],
+    output: &mut Vec<u8>,
+) -> Result<(), small::String> {
+    let mut arguments = Arguments { args, index: 0, error: None };
+    loop {
+        let start = arguments.index;
+        if !format_once(format.as_bytes(), &mut arguments, output)? {
+            break;
+        }
+        // The format is not repeated if it did not consume any arguments.
+        if arguments.index == start || arguments.index >= args.len() {
+            break;
+        }
+    }
+
+    match arguments.error {
+        Some(why) => Err(why),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(format: &str, args: &[&str]) -> String {
+        let args: Vec<small::String> = args.iter().map(|&arg| arg.into()).collect();
+        let mut output = Vec::new();
+        let _ = printf(format, &args, &mut output);
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(format("%s-%s\\n", &["a", "b"]), "a-b\n");
+        assert_eq!(format("[%5s][%-5s][%.2s]", &["ab", "cd", "efgh"]), "[   ab][cd   ][ef]");
+        assert_eq!(format("%s,", &["a", "b", "c"]), "a,b,c,");
+        assert_eq!(format("%s %s;", &["a", "b", "c"]), "a b;c ;");
+        assert_eq!(format("%c%c", &["xyz", "é"]), "xé");
+        assert_eq!(format("%*s|%-*s|", &["4", "a", "3", "b"]), "   a|b  |");
+        assert_eq!(format("100%%", &["ignored"]), "100%");
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(format("%d %i %u", &["42", "-7", "3"]), "42 -7 3");
+        assert_eq!(
+            format("[%5d][%-5d][%05d][%+d][% d]", &["1", "2", "-3", "4", "5"]),
+            "[    1][2    ][-0003][+4][ 5]"
+        );
+        assert_eq!(
+            format("%x %X %o %#x %#o", &["255", "255", "8", "255", "8"]),
+            "ff FF 10 0xff 010"
+        );
+        assert_eq!(format("%d %d %d", &["0x10", "010", "'A"]), "16 8 65");
+        assert_eq!(format("%.3d %.0d|", &["5", "0"]), "005 |");
+        assert_eq!(format("%u", &["-1"]), "18446744073709551615");
+        assert_eq!(format("%d", &[]), "0");
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(
+            format("%f %.2f %8.3f", &["1.5", "2.346", "-3.14159"]),
+            "1.500000 2.35   -3.142"
+        );
+        assert_eq!(format("%e %.2E", &["12345.678", "0.000123"]), "1.234568e+04 1.23E-04");
+        assert_eq!(
+            format("%g %g %g %g", &["100000", "1000000", "0.0001", "1.50"]),
+            "100000 1e+06 0.0001 1.5"
+        );
+        assert_eq!(format("%#g %G", &["1.5", "0.00001"]), "1.50000 1E-05");
+        assert_eq!(format("%f %5.1f", &["inf", "-inf"]), "inf  -inf");
+        assert_eq!(format("%08.2f", &["-1.5"]), "-0001.50");
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(format("a\\tb\\x41\\101\\u00e9", &[]), "a\tbAAé");
+        assert_eq!(format("%b|", &["a\\nb\\0101"]), "a\nbA|");
+        assert_eq!(format("%b%s", &["stop\\chere", "never"]), "stop");
+        assert_eq!(format("\\z\\", &[]), "\\z\\");
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(
+            format("%q %q %q", &["plain", "two words", "it's"]),
+            r"plain 'two words' 'it'\''s'"
+        );
+        assert_eq!(format("%q", &[""]), "''");
+    }
+
+    #[test]
+    fn errors() {
+        let mut output = Vec::new();
+        let args = ["abc".into(), "2".into()];
+        assert!(printf("%d %d", &args, &mut output).is_err());
+        assert_eq!(output, b"0 2");
+        assert!(printf("%z", &[], &mut Vec::new()).is_err());
+        assert!(printf("%5", &[], &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn bounds() {
+        let error = |format: &str, args: &[&str]| {
+            let args: Vec<small::String> = args.iter().map(|&arg| arg.into()).collect();
+            printf(format, &args, &mut Vec::new()).unwrap_err().to_string()
+        };
+        assert_eq!(
+            error("%*s", &["-9223372036854775808", "x"]),
+            "-9223372036854775808: invalid field width"
+        );
+        assert_eq!(error("%*s", &["2147483648", "x"]), "2147483648: invalid field width");
+        assert_eq!(error("%.*s", &["99999999999", "x"]), "99999999999: invalid precision");
+        assert_eq!(error("%99999999999s", &["x"]), "%99999999999: invalid field width");
+        assert_eq!(error("%.99999999999d", &["1"]), "%.99999999999: invalid precision");
+        assert_eq!(format("%*s|%.*s|", &["-3", "a", "-1", "bc"]), "a  |bc|");
+    }
+}
@@ -310,10 +310,8 @@ fn file_has_write_permission(filepath: &str) -> bool {
 /// Exits SUCCESS if the file has execute permissions. This function is rather low level because
 /// Rust currently does not have a higher level abstraction for obtaining non-standard file modes.
 /// To extract the permissions from the mode, the bitwise AND operator will be used and compared
-/// with the respective execute bits.
-/// Note: This function is 1:1 the same as src/builtins/exists.rs:file_has_execute_permission
-/// If you change the following function, please also update the one in src/builtins/exists.rs
-fn file_has_execute_permission(filepath: &str) -> bool {
+/// with the respective execute bits. The shell also uses this to find the commands in its `PATH`.
+pub fn file_has_execute_permission<P: AsRef<Path>>(filepath: P) -> bool {
     const USER: u32 = 0b100_0000;
     const GROUP: u32 = 0b1000;
     const GUEST: u32 = 0b1;
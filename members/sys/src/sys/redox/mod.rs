@@ -15,6 +15,7 @@ use std::{
     path::PathBuf,
     process::exit,
     slice,
+    time::Duration,
 };
 use syscall::{waitpid as waitpid_, SigAction};
 pub use syscall::{
@@ -26,14 +27,68 @@ pub use syscall::{
 pub const PATH_SEPARATOR: &str = ";";
 pub const NULL_PATH: &str = "null:";
 
+/// Descriptors below this number are left to scripts, as in `exec 3< input`.
+pub const SHELL_FD_MIN: RawFd = 10;
+
 pub const O_CLOEXEC: usize = syscall::O_CLOEXEC;
+pub const O_NONBLOCK: usize = syscall::O_NONBLOCK;
+
+pub const RUSAGE_SELF: i32 = 0;
+pub const RUSAGE_CHILDREN: i32 = -1;
+
+pub const RLIM_INFINITY: u64 = !0;
+pub const RLIMIT_CPU: i32 = 0;
+pub const RLIMIT_FSIZE: i32 = 1;
+pub const RLIMIT_DATA: i32 = 2;
+pub const RLIMIT_STACK: i32 = 3;
+pub const RLIMIT_CORE: i32 = 4;
+pub const RLIMIT_RSS: i32 = 5;
+pub const RLIMIT_NPROC: i32 = 6;
+pub const RLIMIT_NOFILE: i32 = 7;
+pub const RLIMIT_MEMLOCK: i32 = 8;
+pub const RLIMIT_AS: i32 = 9;
 pub const SIGHUP: i32 = syscall::SIGHUP as i32;
 pub const SIGINT: i32 = syscall::SIGINT as i32;
 pub const SIGTERM: i32 = syscall::SIGTERM as i32;
 pub const SIGCONT: i32 = syscall::SIGCONT as i32;
+pub const SIGKILL: i32 = syscall::SIGKILL as i32;
 pub const SIGSTOP: i32 = syscall::SIGSTOP as i32;
 pub const SIGTSTP: i32 = syscall::SIGTSTP as i32;
 pub const SIGPIPE: i32 = syscall::SIGPIPE as i32;
+pub const SIGCHLD: i32 = syscall::SIGCHLD as i32;
+
+/// The signals that may be referred to by name, without the `SIG` prefix.
+pub const SIGNALS: &[(&str, i32)] = &[
+    ("HUP", syscall::SIGHUP as i32),
+    ("INT", syscall::SIGINT as i32),
+    ("QUIT", syscall::SIGQUIT as i32),
+    ("ILL", syscall::SIGILL as i32),
+    ("TRAP", syscall::SIGTRAP as i32),
+    ("ABRT", syscall::SIGABRT as i32),
+    ("BUS", syscall::SIGBUS as i32),
+    ("FPE", syscall::SIGFPE as i32),
+    ("KILL", syscall::SIGKILL as i32),
+    ("USR1", syscall::SIGUSR1 as i32),
+    ("SEGV", syscall::SIGSEGV as i32),
+    ("USR2", syscall::SIGUSR2 as i32),
+    ("PIPE", syscall::SIGPIPE as i32),
+    ("ALRM", syscall::SIGALRM as i32),
+    ("TERM", syscall::SIGTERM as i32),
+    ("CHLD", syscall::SIGCHLD as i32),
+    ("CONT", syscall::SIGCONT as i32),
+    ("STOP", syscall::SIGSTOP as i32),
+    ("TSTP", syscall::SIGTSTP as i32),
+    ("TTIN", syscall::SIGTTIN as i32),
+    ("TTOU", syscall::SIGTTOU as i32),
+    ("URG", syscall::SIGURG as i32),
+    ("XCPU", syscall::SIGXCPU as i32),
+    ("XFSZ", syscall::SIGXFSZ as i32),
+    ("VTALRM", syscall::SIGVTALRM as i32),
+    ("PROF", syscall::SIGPROF as i32),
+    ("WINCH", syscall::SIGWINCH as i32),
+    ("IO", syscall::SIGIO as i32),
+    ("SYS", syscall::SIGSYS as i32),
+];
 pub const WUNTRACED: i32 = syscall::WUNTRACED as i32;
 pub const WNOHANG: i32 = syscall::WNOHANG as i32;
 pub const WCONTINUED: i32 = syscall::WCONTINUED as i32;
@@ -78,6 +133,34 @@ pub fn strerror(errno: i32) -> &'static str {
 
 pub fn getpid() -> io::Result<u32> { cvt(syscall::getpid()).map(|pid| pid as u32) }
 
+// The Redox kernel has no system calls for resource usage, resource limits, or file mode creation
+// masks, so `ulimit`, `umask`, and `times` fail there, and `time` reports no CPU time.
+/// Waits for a child as `waitpid` does. Resource usage is not supported, so the CPU times of the
+/// child are given as zero.
+pub fn wait4(pid: i32, status: &mut i32, options: i32) -> Result<(i32, (Duration, Duration)), i32> {
+    waitpid(pid, status, options).map(|pid| (pid, (Duration::default(), Duration::default())))
+}
+
+pub fn getrusage(_who: i32) -> io::Result<(Duration, Duration)> {
+    Err(io::Error::new(io::ErrorKind::Other, "resource usage is not supported"))
+}
+
+pub fn getrlimit(_resource: i32) -> io::Result<(u64, u64)> {
+    Err(io::Error::new(io::ErrorKind::Other, "resource limits are not supported"))
+}
+
+pub fn setrlimit(_resource: i32, _soft: u64, _hard: u64) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Other, "resource limits are not supported"))
+}
+
+pub fn umask(_mask: u32) -> io::Result<u32> {
+    Err(io::Error::new(io::ErrorKind::Other, "file mode creation masks are not supported"))
+}
+
+pub fn getpgid(pid: u32) -> io::Result<u32> {
+    cvt(syscall::getpgid(pid as usize)).map(|pgid| pgid as u32)
+}
+
 pub fn kill(pid: u32, signal: i32) -> io::Result<()> {
     cvt(syscall::kill(pid as usize, signal as usize)).and(Ok(()))
 }
@@ -98,6 +181,7 @@ pub fn setpgid(pid: u32, pgid: u32) -> io::Result<()> {
 
 pub fn fork_and_exec<F: Fn(), S: AsRef<str>>(
     prog: &str,
+    path: Option<&str>,
     args: &[S],
     stdin: Option<RawFd>,
     stdout: Option<RawFd>,
@@ -125,7 +209,7 @@ pub fn fork_and_exec<F: Fn(), S: AsRef<str>>(
 
                 before_exec();
 
-                let error = execve(prog, args, clear_env);
+                let error = execve(prog, path, args, clear_env);
                 eprintln!("ion: command exec: {}", error);
                 fork_exit(1);
             }
@@ -148,9 +232,17 @@ pub fn fork_and_exec<F: Fn(), S: AsRef<str>>(
     }
 }
 
-pub fn execve<S: AsRef<str>>(prog: &str, args: &[S], clear_env: bool) -> io::Error {
-    // Get the PathBuf of the program if it exists.
-    let prog = if prog.contains(':') || prog.contains('/') {
+pub fn execve<S: AsRef<str>>(
+    prog: &str,
+    path: Option<&str>,
+    args: &[S],
+    clear_env: bool,
+) -> io::Error {
+    // Get the PathBuf of the program if it exists. If the caller has already found the program,
+    // its path is given.
+    let prog = if let Some(path) = path {
+        Some(PathBuf::from(path))
+    } else if prog.contains(':') || prog.contains('/') {
         // This is a fully specified scheme or path to an
         // executable.
         Some(PathBuf::from(prog))
@@ -328,6 +420,10 @@ pub fn dup(fd: RawFd) -> io::Result<RawFd> { cvt(syscall::dup(fd, &[])) }
 
 pub fn dup2(old: RawFd, new: RawFd) -> io::Result<RawFd> { cvt(syscall::dup2(old, new, &[])) }
 
+// TODO: Redox is unable to duplicate a descriptor to a minimum number, so the descriptors which
+// the shell holds for itself are left where they are.
+pub fn raise_fd(fd: RawFd) -> RawFd { fd }
+
 pub fn close(fd: RawFd) -> io::Result<()> { cvt(syscall::close(fd)).and(Ok(())) }
 
 pub fn isatty(fd: RawFd) -> bool {
@@ -339,6 +435,17 @@ pub fn isatty(fd: RawFd) -> bool {
     }
 }
 
+// TODO: Redox does not yet support changing the modes of terminals, or polling for input.
+pub struct TerminalMode;
+
+pub fn terminal_mode(_fd: RawFd, _echo: bool, _canonical: bool) -> io::Result<TerminalMode> {
+    Err(io::Error::new(io::ErrorKind::Other, "terminal modes are not supported"))
+}
+
+pub fn poll_input(_fd: RawFd, _timeout: Duration) -> io::Result<bool> {
+    Err(io::Error::new(io::ErrorKind::Other, "polling for input is not supported"))
+}
+
 // Support function for converting syscall error to io error
 fn cvt(result: Result<usize, syscall::Error>) -> io::Result<usize> {
     result.map_err(|err| io::Error::from_raw_os_error(err.errno))
@@ -352,6 +459,9 @@ pub mod signals {
     /// controlled
     /// by the shell.
     pub fn unblock() {}
+
+    /// Unblocks the SIGCHLD signal for the calling thread alone.
+    pub fn unblock_child() {}
 }
 
 pub mod variables {
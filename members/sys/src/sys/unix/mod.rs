@@ -12,24 +12,80 @@ use libc::{
 pub use libc::{ECHILD, EINTR, WCONTINUED, WNOHANG, WUNTRACED};
 
 use std::{
+    cmp,
     env::{split_paths, var, vars},
     ffi::{CStr, CString},
-    io,
+    io, mem,
     os::unix::io::RawFd,
     ptr,
+    time::Duration,
 };
 
 pub const PATH_SEPARATOR: &str = ":";
 pub const NULL_PATH: &str = "/dev/null";
 
+/// Descriptors below this number are left to scripts, as in `exec 3< input`.
+pub const SHELL_FD_MIN: RawFd = 10;
+
 pub const O_CLOEXEC: usize = libc::O_CLOEXEC as usize;
+pub const O_NONBLOCK: usize = libc::O_NONBLOCK as usize;
+
+pub const RUSAGE_SELF: i32 = libc::RUSAGE_SELF;
+pub const RUSAGE_CHILDREN: i32 = libc::RUSAGE_CHILDREN;
+
+pub const RLIM_INFINITY: u64 = libc::RLIM_INFINITY as u64;
+pub const RLIMIT_AS: i32 = libc::RLIMIT_AS as i32;
+pub const RLIMIT_CORE: i32 = libc::RLIMIT_CORE as i32;
+pub const RLIMIT_CPU: i32 = libc::RLIMIT_CPU as i32;
+pub const RLIMIT_DATA: i32 = libc::RLIMIT_DATA as i32;
+pub const RLIMIT_FSIZE: i32 = libc::RLIMIT_FSIZE as i32;
+pub const RLIMIT_MEMLOCK: i32 = libc::RLIMIT_MEMLOCK as i32;
+pub const RLIMIT_NOFILE: i32 = libc::RLIMIT_NOFILE as i32;
+pub const RLIMIT_NPROC: i32 = libc::RLIMIT_NPROC as i32;
+pub const RLIMIT_RSS: i32 = libc::RLIMIT_RSS as i32;
+pub const RLIMIT_STACK: i32 = libc::RLIMIT_STACK as i32;
 pub const SIGHUP: i32 = libc::SIGHUP;
 pub const SIGINT: i32 = libc::SIGINT;
 pub const SIGTERM: i32 = libc::SIGTERM;
 pub const SIGCONT: i32 = libc::SIGCONT;
+pub const SIGKILL: i32 = libc::SIGKILL;
 pub const SIGSTOP: i32 = libc::SIGSTOP;
 pub const SIGTSTP: i32 = libc::SIGTSTP;
 pub const SIGPIPE: i32 = libc::SIGPIPE;
+pub const SIGCHLD: i32 = libc::SIGCHLD;
+
+/// The signals that may be referred to by name, without the `SIG` prefix.
+pub const SIGNALS: &[(&str, i32)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("ILL", libc::SIGILL),
+    ("TRAP", libc::SIGTRAP),
+    ("ABRT", libc::SIGABRT),
+    ("BUS", libc::SIGBUS),
+    ("FPE", libc::SIGFPE),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("SEGV", libc::SIGSEGV),
+    ("USR2", libc::SIGUSR2),
+    ("PIPE", libc::SIGPIPE),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("CHLD", libc::SIGCHLD),
+    ("CONT", libc::SIGCONT),
+    ("STOP", libc::SIGSTOP),
+    ("TSTP", libc::SIGTSTP),
+    ("TTIN", libc::SIGTTIN),
+    ("TTOU", libc::SIGTTOU),
+    ("URG", libc::SIGURG),
+    ("XCPU", libc::SIGXCPU),
+    ("XFSZ", libc::SIGXFSZ),
+    ("VTALRM", libc::SIGVTALRM),
+    ("PROF", libc::SIGPROF),
+    ("WINCH", libc::SIGWINCH),
+    ("IO", libc::SIGIO),
+    ("SYS", libc::SIGSYS),
+];
 
 pub const STDOUT_FILENO: i32 = libc::STDOUT_FILENO;
 pub const STDERR_FILENO: i32 = libc::STDERR_FILENO;
@@ -67,6 +123,16 @@ pub fn waitpid(pid: i32, status: &mut i32, options: i32) -> Result<i32, i32> {
     }
 }
 
+/// Waits for a child as `waitpid` does, also returning the user and system CPU times consumed
+/// by the child, and by those of its own children which it waited upon.
+pub fn wait4(pid: i32, status: &mut i32, options: i32) -> Result<(i32, (Duration, Duration)), i32> {
+    let mut usage: libc::rusage = unsafe { mem::zeroed() };
+    match unsafe { libc::wait4(pid, status, options, &mut usage) } {
+        -1 => Err(errno()),
+        pid => Ok((pid, (duration(usage.ru_utime), duration(usage.ru_stime)))),
+    }
+}
+
 pub fn wexitstatus(status: i32) -> i32 { unsafe { WEXITSTATUS(status) } }
 pub fn wifexited(status: i32) -> bool { unsafe { WIFEXITED(status) } }
 pub fn wifstopped(status: i32) -> bool { unsafe { WIFSTOPPED(status) } }
@@ -88,6 +154,40 @@ pub fn fork_exit(exit_status: i32) -> ! { unsafe { libc::_exit(exit_status) } }
 
 pub fn getpid() -> io::Result<u32> { cvt(unsafe { libc::getpid() }).map(|pid| pid as u32) }
 
+/// Returns the user and system CPU times consumed by the process, or by those of its children
+/// which have been waited upon.
+pub fn getrusage(who: i32) -> io::Result<(Duration, Duration)> {
+    let mut usage: libc::rusage = unsafe { mem::zeroed() };
+    cvt(unsafe { libc::getrusage(who, &mut usage) })?;
+    Ok((duration(usage.ru_utime), duration(usage.ru_stime)))
+}
+
+fn duration(time: libc::timeval) -> Duration {
+    Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1000)
+}
+
+/// Returns the soft and hard limits of the resource.
+pub fn getrlimit(resource: i32) -> io::Result<(u64, u64)> {
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    cvt(unsafe { libc::getrlimit(resource as _, &mut limit) })?;
+    Ok((limit.rlim_cur as u64, limit.rlim_max as u64))
+}
+
+/// Sets the soft and hard limits of the resource.
+pub fn setrlimit(resource: i32, soft: u64, hard: u64) -> io::Result<()> {
+    let limit = libc::rlimit { rlim_cur: soft as libc::rlim_t, rlim_max: hard as libc::rlim_t };
+    cvt(unsafe { libc::setrlimit(resource as _, &limit) }).and(Ok(()))
+}
+
+/// Sets the file mode creation mask of the process, returning the previous mask.
+pub fn umask(mask: u32) -> io::Result<u32> {
+    Ok(unsafe { libc::umask(mask as libc::mode_t) } as u32)
+}
+
+pub fn getpgid(pid: u32) -> io::Result<u32> {
+    cvt(unsafe { libc::getpgid(pid as pid_t) }).map(|pgid| pgid as u32)
+}
+
 pub fn kill(pid: u32, signal: i32) -> io::Result<()> {
     cvt(unsafe { libc::kill(pid as pid_t, signal as c_int) }).and(Ok(()))
 }
@@ -96,8 +196,36 @@ pub fn killpg(pgid: u32, signal: i32) -> io::Result<()> {
     cvt(unsafe { libc::kill(-(pgid as pid_t), signal as c_int) }).and(Ok(()))
 }
 
+/// Get the path of the program if it exists. If the caller has already found the program, its
+/// path is given; otherwise the `PATH` is searched.
+fn program_path(prog: &str, prog_str: CString, path: Option<&str>) -> Option<CString> {
+    if let Some(path) = path {
+        CString::new(path).ok()
+    } else if prog.contains('/') {
+        // This is a fully specified path to an executable.
+        Some(prog_str)
+    } else if let Ok(paths) = var("PATH") {
+        // This is not a fully specified scheme or path.
+        // Iterate through the possible paths in the
+        // env var PATH that this executable may be found
+        // in and return the first one found.
+        split_paths(&paths)
+            .filter_map(|mut path| {
+                path.push(prog);
+                match (path.exists(), path.to_str()) {
+                    (true, Some(path)) => CString::new(path).ok(),
+                    _ => None,
+                }
+            })
+            .next()
+    } else {
+        None
+    }
+}
+
 pub fn fork_and_exec<F: Fn(), S: AsRef<str>>(
     prog: &str,
+    path: Option<&str>,
     args: &[S],
     stdin: Option<RawFd>,
     stdout: Option<RawFd>,
@@ -128,27 +256,7 @@ pub fn fork_and_exec<F: Fn(), S: AsRef<str>>(
     let mut arg_ptrs: Vec<*const c_char> = cvt_args.iter().map(|x| x.as_ptr()).collect();
     arg_ptrs.push(ptr::null());
 
-    // Get the PathBuf of the program if it exists.
-    let prog = if prog.contains('/') {
-        // This is a fully specified path to an executable.
-        Some(prog_str)
-    } else if let Ok(paths) = var("PATH") {
-        // This is not a fully specified scheme or path.
-        // Iterate through the possible paths in the
-        // env var PATH that this executable may be found
-        // in and return the first one found.
-        split_paths(&paths)
-            .filter_map(|mut path| {
-                path.push(prog);
-                match (path.exists(), path.to_str()) {
-                    (true, Some(path)) => CString::new(path).ok(),
-                    _ => None,
-                }
-            })
-            .next()
-    } else {
-        None
-    };
+    let prog = program_path(prog, prog_str, path);
 
     let mut env_ptrs: Vec<*const c_char> = Vec::new();
     let mut env_vars: Vec<CString> = Vec::new();
@@ -214,7 +322,12 @@ pub fn fork_and_exec<F: Fn(), S: AsRef<str>>(
     }
 }
 
-pub fn execve<S: AsRef<str>>(prog: &str, args: &[S], clear_env: bool) -> io::Error {
+pub fn execve<S: AsRef<str>>(
+    prog: &str,
+    path: Option<&str>,
+    args: &[S],
+    clear_env: bool,
+) -> io::Error {
     let prog_str = match CString::new(prog) {
         Ok(prog) => prog,
         Err(_) => {
@@ -238,27 +351,7 @@ pub fn execve<S: AsRef<str>>(prog: &str, args: &[S], clear_env: bool) -> io::Err
     let mut arg_ptrs: Vec<*const c_char> = cvt_args.iter().map(|x| x.as_ptr()).collect();
     arg_ptrs.push(ptr::null());
 
-    // Get the PathBuf of the program if it exists.
-    let prog = if prog.contains('/') {
-        // This is a fully specified path to an executable.
-        Some(prog_str)
-    } else if let Ok(paths) = var("PATH") {
-        // This is not a fully specified scheme or path.
-        // Iterate through the possible paths in the
-        // env var PATH that this executable may be found
-        // in and return the first one found.
-        split_paths(&paths)
-            .filter_map(|mut path| {
-                path.push(prog);
-                match (path.exists(), path.to_str()) {
-                    (true, Some(path)) => CString::new(path).ok(),
-                    _ => None,
-                }
-            })
-            .next()
-    } else {
-        None
-    };
+    let prog = program_path(prog, prog_str, path);
 
     let mut env_ptrs: Vec<*const c_char> = Vec::new();
     let mut env_vars: Vec<CString> = Vec::new();
@@ -328,10 +421,70 @@ pub fn dup(fd: RawFd) -> io::Result<RawFd> { cvt(unsafe { libc::dup(fd) }) }
 
 pub fn dup2(old: RawFd, new: RawFd) -> io::Result<RawFd> { cvt(unsafe { libc::dup2(old, new) }) }
 
+/// Moves a descriptor which the shell holds for itself to the lowest free descriptor that is at
+/// least `SHELL_FD_MIN`, so that it is not replaced by the redirections of a script. The moved
+/// descriptor is closed on exec. If it cannot be moved, the original descriptor is returned.
+pub fn raise_fd(fd: RawFd) -> RawFd {
+    if fd >= SHELL_FD_MIN {
+        return fd;
+    }
+
+    match cvt(unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, SHELL_FD_MIN) }) {
+        Ok(raised) => {
+            let _ = close(fd);
+            raised
+        }
+        Err(_) => fd,
+    }
+}
+
 pub fn close(fd: RawFd) -> io::Result<()> { cvt(unsafe { libc::close(fd) }).and(Ok(())) }
 
 pub fn isatty(fd: RawFd) -> bool { unsafe { libc::isatty(fd) == 1 } }
 
+/// The attributes of a terminal, which are restored when the value is dropped.
+pub struct TerminalMode {
+    fd:         RawFd,
+    attributes: libc::termios,
+}
+
+impl Drop for TerminalMode {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(self.fd, libc::TCSANOW, &self.attributes);
+        }
+    }
+}
+
+/// Sets whether the terminal echoes its input, and whether its input is given line by line,
+/// until the returned value is dropped. Without canonical input, each byte is given as it is
+/// typed.
+pub fn terminal_mode(fd: RawFd, echo: bool, canonical: bool) -> io::Result<TerminalMode> {
+    let mut attributes: libc::termios = unsafe { mem::zeroed() };
+    cvt(unsafe { libc::tcgetattr(fd, &mut attributes) })?;
+
+    let mut mode = attributes;
+    if !echo {
+        mode.c_lflag &= !libc::ECHO;
+    }
+    if !canonical {
+        mode.c_lflag &= !libc::ICANON;
+        mode.c_cc[libc::VMIN] = 1;
+        mode.c_cc[libc::VTIME] = 0;
+    }
+    cvt(unsafe { libc::tcsetattr(fd, libc::TCSANOW, &mode) })?;
+    Ok(TerminalMode { fd, attributes })
+}
+
+/// Waits for input to become available to be read from the file descriptor, returning `false`
+/// if the timeout passed before it did.
+pub fn poll_input(fd: RawFd, timeout: Duration) -> io::Result<bool> {
+    let mut poll = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+    let millis = timeout.as_secs().saturating_mul(1000) + u64::from(timeout.subsec_millis());
+    let millis = cmp::min(millis, c_int::max_value() as u64) as c_int;
+    cvt(unsafe { libc::poll(&mut poll, 1, millis) }).map(|ready| ready > 0)
+}
+
 trait IsMinusOne {
     fn is_minus_one(&self) -> bool;
 }
@@ -29,3 +29,14 @@ pub fn unblock() {
         sigprocmask(SIG_UNBLOCK, &sigset as *const sigset_t, ptr::null_mut() as *mut sigset_t);
     }
 }
+
+/// Unblocks the SIGCHLD signal for the calling thread alone, so that the thread may be
+/// notified when the state of a child changes.
+pub fn unblock_child() {
+    unsafe {
+        let mut sigset = mem::uninitialized::<sigset_t>();
+        sigemptyset(&mut sigset as *mut sigset_t);
+        sigaddset(&mut sigset as *mut sigset_t, SIGCHLD);
+        pthread_sigmask(SIG_UNBLOCK, &sigset as *const sigset_t, ptr::null_mut() as *mut sigset_t);
+    }
+}
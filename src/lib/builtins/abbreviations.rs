@@ -0,0 +1,163 @@
+use std::{
+    fs,
+    io::{self, Write},
+};
+
+use crate::{
+    shell::{status::*, Shell},
+    types,
+};
+use small;
+use xdg::BaseDirectories;
+
+/// Quotes the expansion so that a listed abbreviation may be evaluated again as-is.
+fn quote(expansion: &str) -> String {
+    if !expansion.contains('\'') {
+        return ["'", expansion, "'"].concat();
+    }
+
+    let mut quoted = String::with_capacity(expansion.len() + 2);
+    quoted.push('"');
+    for character in expansion.chars() {
+        match character {
+            '"' | '\\' | '$' | '@' => quoted.push('\\'),
+            _ => (),
+        }
+        quoted.push(character);
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn print_list(shell: &Shell) {
+    let stdout = io::stdout();
+    let stdout = &mut stdout.lock();
+
+    for (name, expansion) in &shell.abbreviations {
+        let _ = writeln!(stdout, "abbr {} {}", name, quote(expansion));
+    }
+}
+
+/// Rewrites the init file so that it no longer defines the abbreviation, and then appends the
+/// new definition of the abbreviation, if one was given.
+fn save(name: &str, expansion: Option<&str>) -> io::Result<()> {
+    let base_dirs = BaseDirectories::with_prefix("ion")?;
+    let initrc = base_dirs.place_config_file("initrc")?;
+    let contents = fs::read_to_string(&initrc).or_else(|why| {
+        if why.kind() == io::ErrorKind::NotFound {
+            Ok(String::new())
+        } else {
+            Err(why)
+        }
+    })?;
+
+    let mut contents = contents
+        .lines()
+        .filter(|line| {
+            let mut words = line.split_whitespace();
+            words.next() != Some("abbr")
+                || words.find(|word| !word.starts_with('-')).map_or(true, |word| word != name)
+        })
+        .fold(String::with_capacity(contents.len()), |mut acc, line| {
+            acc.push_str(line);
+            acc.push('\n');
+            acc
+        });
+
+    if let Some(expansion) = expansion {
+        contents.push_str(&["abbr ", name, " ", &quote(expansion), "\n"].concat());
+    }
+
+    fs::write(&initrc, contents)
+}
+
+/// The `abbr` command manages abbreviations, which are expanded in place within the line editor
+/// when the user presses space or enter after typing one as a command.
+pub(crate) fn abbr(args: &[small::String], shell: &mut Shell) -> i32 {
+    let mut erase = false;
+    let mut persist = false;
+    let mut args = &args[1..];
+    while let Some(arg) = args.first() {
+        match arg.as_str() {
+            "-e" | "--erase" => erase = true,
+            "-s" | "--save" => persist = true,
+            "--" => {
+                args = &args[1..];
+                break;
+            }
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                eprintln!("ion: abbr: invalid option: {}", flag);
+                return BAD_ARG;
+            }
+            _ => break,
+        }
+        args = &args[1..];
+    }
+
+    if erase {
+        if args.is_empty() {
+            eprintln!("ion: abbr: you must specify an abbreviation name");
+            return FAILURE;
+        }
+
+        let mut status = SUCCESS;
+        for name in args {
+            if shell.abbreviations.remove(name.as_str()).is_none() {
+                eprintln!("ion: abbr: undefined abbreviation: {}", name);
+                status = FAILURE;
+            } else if persist {
+                if let Err(why) = save(name, None) {
+                    eprintln!("ion: abbr: could not update the init file: {}", why);
+                    status = FAILURE;
+                }
+            }
+        }
+        return status;
+    }
+
+    let (name, expansion) = match args.split_first() {
+        Some((name, expansion)) => (name, expansion),
+        None => {
+            print_list(shell);
+            return SUCCESS;
+        }
+    };
+
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        eprintln!("ion: abbr: abbreviation name, '{}', is invalid", name);
+        return FAILURE;
+    }
+
+    if expansion.is_empty() {
+        match shell.abbreviations.get(name.as_str()) {
+            Some(expansion) => println!("abbr {} {}", name, quote(expansion)),
+            None => {
+                eprintln!("ion: abbr: undefined abbreviation: {}", name);
+                return FAILURE;
+            }
+        }
+        return SUCCESS;
+    }
+
+    let expansion: Vec<&str> = expansion.iter().map(|arg| arg.as_str()).collect();
+    let expansion = types::Str::from(expansion.join(" "));
+    if persist {
+        if let Err(why) = save(name, Some(&expansion)) {
+            eprintln!("ion: abbr: could not update the init file: {}", why);
+            return FAILURE;
+        }
+    }
+    shell.abbreviations.insert(types::Str::from(name.as_str()), expansion);
+    SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote("git checkout"), "'git checkout'");
+        assert_eq!(quote("echo 'a' $b"), "\"echo 'a' \\$b\"");
+    }
+}
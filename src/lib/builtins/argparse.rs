@@ -0,0 +1,344 @@
+//! Contains the `argparse` builtin, which parses the arguments of scripts and functions
+//! according to a declarative specification of their options.
+
+use crate::{
+    shell::{status::*, variables::Variables, Shell},
+    types,
+};
+use small;
+use std::fmt::Write;
+
+/// The exit status once the help text was displayed, which differs from that of invalid
+/// arguments, so that `argparse ... || exit` ends a script without continuing after the help.
+const HELP_DISPLAYED: i32 = 3;
+
+/// Whether, and how, an option accepts a value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    /// A flag without a value, as in `v/verbose`.
+    Flag,
+    /// An option which requires a value, as in `o/output=`.
+    Required,
+    /// An option which may be given a value with `--name=value` or `-nvalue`, as in `level=?`.
+    Optional,
+    /// An option which requires a value, and which may be repeated, as in `I/include=+`.
+    Repeated,
+}
+
+/// An option of the specification, such as `o/output=`.
+#[derive(Debug, PartialEq)]
+struct Spec<'a> {
+    short: Option<char>,
+    long:  Option<&'a str>,
+    kind:  Kind,
+}
+
+impl<'a> Spec<'a> {
+    fn parse(spec: &'a str) -> Result<Spec<'a>, String> {
+        let (names, kind) = if spec.ends_with("=?") {
+            (&spec[..spec.len() - 2], Kind::Optional)
+        } else if spec.ends_with("=+") {
+            (&spec[..spec.len() - 2], Kind::Repeated)
+        } else if spec.ends_with('=') {
+            (&spec[..spec.len() - 1], Kind::Required)
+        } else {
+            (spec, Kind::Flag)
+        };
+
+        let (short, long) = match names.find('/') {
+            Some(slash) => (&names[..slash], Some(&names[slash + 1..])),
+            None if names.chars().count() == 1 => (names, None),
+            None => ("", Some(names)),
+        };
+
+        let mut characters = short.chars();
+        let short = match (characters.next(), characters.next()) {
+            (None, _) => None,
+            (Some(short), None) if short.is_alphanumeric() => Some(short),
+            _ => return Err(format!("{}: short options must be a single character", spec)),
+        };
+        let valid = |name: &str| {
+            !name.is_empty()
+                && !name.starts_with('-')
+                && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        };
+        match long {
+            Some(long) if !valid(long) => Err(format!("{}: invalid long option", spec)),
+            _ => Ok(Spec { short, long, kind }),
+        }
+    }
+
+    /// The name of the variable which the option is stored in, such as `flag_output`.
+    fn variable(&self) -> String {
+        match (self.long, self.short) {
+            (Some(long), _) => format!("flag_{}", long.replace('-', "_")),
+            (None, Some(short)) => format!("flag_{}", short),
+            (None, None) => String::from("flag_"),
+        }
+    }
+
+    /// Describes the option as it is given, as in `-o, --output VALUE`.
+    fn usage(&self) -> String {
+        let mut usage = match (self.short, self.long) {
+            (Some(short), Some(long)) => format!("-{}, --{}", short, long),
+            (Some(short), None) => format!("-{}", short),
+            (None, Some(long)) => format!("    --{}", long),
+            (None, None) => String::new(),
+        };
+        usage.push_str(match self.kind {
+            Kind::Flag => "",
+            Kind::Required => " VALUE",
+            Kind::Optional if self.long.is_some() => "[=VALUE]",
+            Kind::Optional => "[VALUE]",
+            Kind::Repeated => " VALUE...",
+        });
+        usage
+    }
+}
+
+/// The values which were given to an option of the specification.
+#[derive(Debug, Default, PartialEq)]
+struct Given<'a> {
+    count:  usize,
+    values: Vec<&'a str>,
+}
+
+/// Parses the arguments according to the specification, returning the values given to each
+/// option, and the positional arguments. Options and positional arguments may be mixed,
+/// until a `--` is given, after which every argument is positional.
+fn parse<'a>(
+    specs: &[Spec],
+    args: &'a [small::String],
+) -> Result<(Vec<Given<'a>>, Vec<&'a str>), String> {
+    let mut given: Vec<Given> = specs.iter().map(|_| Given::default()).collect();
+    let mut positional = Vec::new();
+
+    let mut args = args.iter().map(|arg| arg.as_str());
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            positional.extend(&mut args);
+            break;
+        } else if arg.starts_with("--") {
+            let (name, value) = match arg.find('=') {
+                Some(equals) => (&arg[2..equals], Some(&arg[equals + 1..])),
+                None => (&arg[2..], None),
+            };
+            let index = specs
+                .iter()
+                .position(|spec| spec.long == Some(name))
+                .ok_or_else(|| format!("{}: unknown option", arg))?;
+
+            let value = match (specs[index].kind, value) {
+                (Kind::Flag, Some(_)) => {
+                    return Err(format!("--{}: does not take a value", name));
+                }
+                (Kind::Required, None) | (Kind::Repeated, None) => Some(
+                    args.next().ok_or_else(|| format!("--{}: requires a value", name))?,
+                ),
+                (_, value) => value,
+            };
+            given[index].count += 1;
+            given[index].values.extend(value);
+        } else if arg.starts_with('-') && arg.len() > 1 {
+            // Short options may be combined, and the last may be followed by its value.
+            for (offset, short) in arg.char_indices().skip(1) {
+                let index = specs
+                    .iter()
+                    .position(|spec| spec.short == Some(short))
+                    .ok_or_else(|| format!("-{}: unknown option", short))?;
+
+                let rest = &arg[offset + short.len_utf8()..];
+                let value = match specs[index].kind {
+                    Kind::Flag => None,
+                    _ if !rest.is_empty() => Some(rest),
+                    Kind::Optional => None,
+                    _ => Some(args.next().ok_or_else(|| format!("-{}: requires a value", short))?),
+                };
+                given[index].count += 1;
+                given[index].values.extend(value);
+                if value.is_some() {
+                    break;
+                }
+            }
+        } else {
+            positional.push(arg);
+        }
+    }
+
+    Ok((given, positional))
+}
+
+/// Generates the help text which describes the options of the specification.
+fn help(name: Option<&str>, specs: &[Spec]) -> String {
+    let mut help = String::from("usage:");
+    if let Some(name) = name {
+        help.push(' ');
+        help.push_str(name);
+    }
+    help.push_str(" [OPTIONS] [ARGUMENTS...]\n\nOPTIONS");
+    for spec in specs {
+        let _ = write!(help, "\n    {}", spec.usage());
+    }
+    help
+}
+
+/// Parses the arguments following `--` according to the options of the specification, which
+/// precede it. Each option is stored in a variable named after it: flags are set to `true` or
+/// `false`, options which take a value are set to the value that was given, or are removed if
+/// none was, and repeated options are set to an array of their values. The positional
+/// arguments which remain are stored in `@args`.
+pub(crate) fn argparse(args: &[small::String], shell: &mut Shell) -> i32 {
+    let mut name = None;
+    let mut index = 0;
+    if args.get(0).map_or(false, |arg| arg == "-n" || arg == "--name") {
+        match args.get(1) {
+            Some(value) => name = Some(value.as_str()),
+            None => {
+                eprintln!("ion: argparse: {} requires an argument", args[0]);
+                return BAD_ARG;
+            }
+        }
+        index = 2;
+    }
+
+    let separator = match args[index..].iter().position(|arg| arg == "--") {
+        Some(separator) => index + separator,
+        None => {
+            eprintln!("ion: argparse: missing -- before the arguments to parse");
+            return BAD_ARG;
+        }
+    };
+
+    let mut specs = Vec::with_capacity(separator - index + 1);
+    for spec in &args[index..separator] {
+        match Spec::parse(spec) {
+            Ok(spec) => specs.push(spec),
+            Err(why) => {
+                eprintln!("ion: argparse: {}", why);
+                return BAD_ARG;
+            }
+        }
+    }
+
+    // Unless the specification defines them itself, `-h` and `--help` display the help text.
+    let automatic_help =
+        specs.iter().all(|spec| spec.short != Some('h') && spec.long != Some("help"));
+    if automatic_help {
+        specs.push(Spec { short: Some('h'), long: Some("help"), kind: Kind::Flag });
+    }
+    for (position, spec) in specs.iter().enumerate() {
+        let variable = spec.variable();
+        if !Variables::is_valid_variable_name(&variable) {
+            eprintln!("ion: argparse: {}: invalid variable name", variable);
+            return BAD_ARG;
+        }
+        let duplicate = specs[..position].iter().any(|other| {
+            (spec.short.is_some() && other.short == spec.short)
+                || (spec.long.is_some() && other.long == spec.long)
+        });
+        if duplicate {
+            eprintln!("ion: argparse: {}: option is specified more than once", spec.usage());
+            return BAD_ARG;
+        }
+    }
+
+    let (given, positional) = match parse(&specs, &args[separator + 1..]) {
+        Ok(parsed) => parsed,
+        Err(why) => {
+            match name {
+                Some(name) => eprintln!("{}: {}", name, why),
+                None => eprintln!("ion: argparse: {}", why),
+            }
+            return FAILURE;
+        }
+    };
+
+    let mut status = SUCCESS;
+    for (position, (spec, given)) in specs.iter().zip(given).enumerate() {
+        let variable = spec.variable();
+        match spec.kind {
+            Kind::Flag => {
+                if automatic_help && position == specs.len() - 1 && given.count != 0 {
+                    println!("{}", help(name, &specs));
+                    status = HELP_DISPLAYED;
+                }
+                shell.variables.set(&variable, if given.count == 0 { "false" } else { "true" });
+            }
+            Kind::Repeated => {
+                let values = given.values.into_iter().map(Into::into).collect::<types::Array>();
+                shell.variables.set(&variable, values);
+            }
+            _ => match given.values.last() {
+                Some(&value) => shell.variables.set(&variable, value),
+                // An optional option which was given without a value is set to be empty.
+                None if given.count != 0 => shell.variables.set(&variable, ""),
+                None => {
+                    shell.variables.remove_variable(&variable);
+                }
+            },
+        }
+    }
+
+    let positional = positional.into_iter().map(Into::into).collect::<types::Array>();
+    shell.variables.set("args", positional);
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<small::String> { args.iter().map(|&arg| arg.into()).collect() }
+
+    #[test]
+    fn specs() {
+        assert_eq!(
+            Spec::parse("o/output="),
+            Ok(Spec { short: Some('o'), long: Some("output"), kind: Kind::Required })
+        );
+        assert_eq!(Spec::parse("v"), Ok(Spec { short: Some('v'), long: None, kind: Kind::Flag }));
+        assert_eq!(
+            Spec::parse("dry-run"),
+            Ok(Spec { short: None, long: Some("dry-run"), kind: Kind::Flag })
+        );
+        assert_eq!(
+            Spec::parse("level=?"),
+            Ok(Spec { short: None, long: Some("level"), kind: Kind::Optional })
+        );
+        assert_eq!(
+            Spec::parse("I/include=+"),
+            Ok(Spec { short: Some('I'), long: Some("include"), kind: Kind::Repeated })
+        );
+        assert!(Spec::parse("ab/long").is_err());
+        assert!(Spec::parse("a/").is_err());
+        assert_eq!(Spec::parse("dry-run").unwrap().variable(), "flag_dry_run");
+        assert_eq!(Spec::parse("I/include=+").unwrap().usage(), "-I, --include VALUE...");
+    }
+
+    #[test]
+    fn parsing() {
+        let specs: Vec<Spec> = ["v/verbose", "o/output=", "I/include=+", "level=?"]
+            .iter()
+            .map(|spec| Spec::parse(spec).unwrap())
+            .collect();
+
+        let input = args(&["-vo", "out", "a", "--include=x", "-Iy", "--level", "b", "--", "-v"]);
+        let (given, positional) = parse(&specs, &input).unwrap();
+        assert_eq!(given[0], Given { count: 1, values: vec![] });
+        assert_eq!(given[1], Given { count: 1, values: vec!["out"] });
+        assert_eq!(given[2], Given { count: 2, values: vec!["x", "y"] });
+        assert_eq!(given[3], Given { count: 1, values: vec![] });
+        assert_eq!(positional, vec!["a", "b", "-v"]);
+
+        let input = args(&["--output"]);
+        assert!(parse(&specs, &input).is_err());
+        let input = args(&["-x"]);
+        assert!(parse(&specs, &input).is_err());
+        let input = args(&["--verbose=yes"]);
+        assert!(parse(&specs, &input).is_err());
+        let input = args(&["-", "--level=2"]);
+        let (given, positional) = parse(&specs, &input).unwrap();
+        assert_eq!(given[3].values, vec!["2"]);
+        assert_eq!(positional, vec!["-"]);
+    }
+}
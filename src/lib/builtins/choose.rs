@@ -0,0 +1,275 @@
+//! Contains the `choose` builtin, which displays a menu of items, and assigns the item that the
+//! user chooses to a variable.
+
+use super::{
+    read::{read_record, wait_for_input, Ending},
+    timeout::interrupted,
+};
+use crate::{
+    shell::{signals, status::*, variables::Variables, Shell},
+    sys,
+};
+use small;
+use std::{
+    fs::File,
+    io::{self, Write},
+    mem::ManuallyDrop,
+    os::unix::io::FromRawFd,
+    sync::atomic::Ordering,
+};
+use termion::{clear, cursor, event::Key, input::TermRead, style, terminal_size};
+
+/// The prompt which is displayed beneath a numbered menu, unless another is given.
+const DEFAULT_PROMPT: &str = "#? ";
+
+/// How the user's input affects a menu which is navigated with the arrow keys.
+#[derive(Debug, PartialEq)]
+enum Action {
+    Select(usize),
+    Choose(usize),
+    Cancel,
+    Ignore,
+}
+
+/// Determines the item which is selected after a key is pressed.
+fn navigate(key: &Key, selected: usize, count: usize) -> Action {
+    match *key {
+        Key::Up | Key::Char('k') | Key::Ctrl('p') => Action::Select(selected.saturating_sub(1)),
+        Key::Down | Key::Char('j') | Key::Ctrl('n') => {
+            Action::Select((selected + 1).min(count - 1))
+        }
+        Key::Home | Key::Char('g') => Action::Select(0),
+        Key::End | Key::Char('G') => Action::Select(count - 1),
+        Key::Char('\n') | Key::Char('\r') => Action::Choose(selected),
+        Key::Char(digit @ '1'..='9') => match choice(&digit.to_string(), count) {
+            Some(index) => Action::Select(index),
+            None => Action::Ignore,
+        },
+        Key::Esc | Key::Char('q') | Key::Ctrl('d') => Action::Cancel,
+        _ => Action::Ignore,
+    }
+}
+
+/// Parses the number of an item from the user's input, returning the index of that item.
+fn choice(input: &str, count: usize) -> Option<usize> {
+    let number = input.trim().parse::<usize>().ok()?;
+    if number >= 1 && number <= count {
+        Some(number - 1)
+    } else {
+        None
+    }
+}
+
+/// Writes each item of the menu, highlighting the selected item.
+fn draw(output: &mut impl Write, items: &[small::String], selected: usize) -> io::Result<()> {
+    for (index, item) in items.iter().enumerate() {
+        if index == selected {
+            writeln!(
+                output,
+                "\r{}{}> {}{}",
+                clear::CurrentLine,
+                style::Invert,
+                item,
+                style::Reset
+            )?;
+        } else {
+            writeln!(output, "\r{}  {}", clear::CurrentLine, item)?;
+        }
+    }
+    output.flush()
+}
+
+/// Displays a menu which is navigated with the arrow keys, returning the index of the item which
+/// was chosen, or `None` if the menu was cancelled.
+fn menu(items: &[small::String], prompt: Option<&str>) -> Result<Option<usize>, i32> {
+    // Keys are read as they are pressed, and are not echoed while the menu is displayed.
+    let _mode = sys::terminal_mode(sys::STDIN_FILENO, false, false).map_err(|why| {
+        eprintln!("ion: choose: {}", why);
+        FAILURE
+    })?;
+    let input = ManuallyDrop::new(unsafe { File::from_raw_fd(sys::STDIN_FILENO) });
+    let stderr = io::stderr();
+    let mut output = stderr.lock();
+
+    let failed = |why: io::Error| {
+        eprintln!("ion: choose: {}", why);
+        FAILURE
+    };
+    if let Some(prompt) = prompt {
+        writeln!(output, "{}", prompt).map_err(failed)?;
+    }
+    let mut selected = 0;
+    draw(&mut output, items, selected).map_err(failed)?;
+
+    // Ctrl-C interrupts the shell as usual, which ends the wait for the next key.
+    let mut keys = (&*input).keys();
+    loop {
+        match wait_for_input(sys::STDIN_FILENO, None) {
+            Some(Ending::Interrupted) => return Err(interrupted()),
+            Some(Ending::Failed(why)) => return Err(failed(why)),
+            _ => (),
+        }
+
+        let key = match keys.next() {
+            Some(Ok(key)) => key,
+            Some(Err(ref why)) if why.kind() == io::ErrorKind::Interrupted => {
+                if signals::PENDING.load(Ordering::SeqCst) != 0 {
+                    return Err(interrupted());
+                }
+                continue;
+            }
+            Some(Err(why)) => return Err(failed(why)),
+            None => return Ok(None),
+        };
+
+        match navigate(&key, selected, items.len()) {
+            Action::Select(index) => selected = index,
+            Action::Choose(index) => return Ok(Some(index)),
+            Action::Cancel => return Ok(None),
+            Action::Ignore => continue,
+        }
+        write!(output, "{}", cursor::Up(items.len() as u16)).map_err(failed)?;
+        draw(&mut output, items, selected).map_err(failed)?;
+    }
+}
+
+/// Reads a line of input, returning `None` at the end of the input.
+fn read_line() -> Result<Option<String>, i32> {
+    let (line, ending) = read_record(sys::STDIN_FILENO, b'\n', None, None);
+    match ending {
+        Ending::EndOfFile if line.is_empty() => Ok(None),
+        Ending::Interrupted => Err(interrupted()),
+        Ending::Failed(why) => {
+            eprintln!("ion: choose: {}", why);
+            Err(FAILURE)
+        }
+        _ => Ok(Some(String::from_utf8_lossy(&line).into_owned())),
+    }
+}
+
+/// Displays a numbered menu, and reads the number of an item, until a valid number is given.
+/// The menu is displayed again whenever an empty line is given.
+fn numbered(items: &[small::String], prompt: Option<&str>) -> Result<Option<usize>, i32> {
+    let width = items.len().to_string().len();
+    let mut display = true;
+    loop {
+        if display {
+            for (index, item) in items.iter().enumerate() {
+                eprintln!("{:>width$}) {}", index + 1, item, width = width);
+            }
+        }
+        eprint!("{}", prompt.unwrap_or(DEFAULT_PROMPT));
+        let _ = io::stderr().flush();
+
+        let line = match read_line()? {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+        display = line.trim().is_empty();
+        match choice(&line, items.len()) {
+            Some(index) => return Ok(Some(index)),
+            None if display => (),
+            None => eprintln!("ion: choose: invalid choice: {}", line.trim()),
+        }
+    }
+}
+
+/// Displays a menu of the items, and assigns the item which the user chooses to the variable,
+/// and its index to the index variable. When the input and the error output are terminals, the
+/// menu is navigated with the arrow keys; otherwise the number of an item is read.
+pub(crate) fn choose(args: &[small::String], shell: &mut Shell) -> i32 {
+    let mut prompt = None;
+    let mut index_variable = None;
+    let mut numbers = false;
+
+    let mut position = 0;
+    while let Some(arg) = args.get(position) {
+        let option = match arg.as_str() {
+            "--" => {
+                position += 1;
+                break;
+            }
+            "-n" => {
+                numbers = true;
+                position += 1;
+                continue;
+            }
+            option @ "-p" | option @ "-i" => option,
+            option if option.starts_with('-') && option.len() > 1 => {
+                eprintln!("ion: choose: invalid option: {}", option);
+                return BAD_ARG;
+            }
+            _ => break,
+        };
+        match args.get(position + 1) {
+            Some(value) if option == "-p" => prompt = Some(value.as_str()),
+            Some(value) => index_variable = Some(value.as_str()),
+            None => {
+                eprintln!("ion: choose: {} requires an argument", option);
+                return BAD_ARG;
+            }
+        }
+        position += 2;
+    }
+
+    let (variable, items) = match args[position..].split_first() {
+        Some((variable, items)) if !items.is_empty() => (variable.as_str(), items),
+        Some(_) => {
+            eprintln!("ion: choose: no items to choose from");
+            return BAD_ARG;
+        }
+        None => {
+            eprintln!("ion: choose: a variable is required");
+            return BAD_ARG;
+        }
+    };
+    let index_variable = index_variable.map_or_else(|| format!("{}_index", variable), Into::into);
+    for name in &[variable, index_variable.as_str()] {
+        if !Variables::is_valid_variable_name(name) {
+            eprintln!("ion: choose: {}: invalid variable name", name);
+            return BAD_ARG;
+        }
+    }
+
+    // The menu is only navigated with keys when every item fits within the terminal.
+    let fits = terminal_size().ok().map_or(false, |(_, rows)| items.len() < rows as usize);
+    let navigable = sys::isatty(sys::STDIN_FILENO) && sys::isatty(sys::STDERR_FILENO) && fits;
+    let chosen = if navigable && !numbers { menu(items, prompt) } else { numbered(items, prompt) };
+
+    match chosen {
+        Ok(Some(index)) => {
+            shell.variables.set(variable, items[index].as_str());
+            shell.variables.set(&index_variable, index.to_string());
+            SUCCESS
+        }
+        Ok(None) => FAILURE,
+        Err(status) => status,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn choices() {
+        assert_eq!(choice("1", 3), Some(0));
+        assert_eq!(choice(" 3 \r", 3), Some(2));
+        assert_eq!(choice("0", 3), None);
+        assert_eq!(choice("4", 3), None);
+        assert_eq!(choice("apple", 3), None);
+        assert_eq!(choice("", 3), None);
+    }
+
+    #[test]
+    fn navigation() {
+        assert_eq!(navigate(&Key::Up, 0, 3), Action::Select(0));
+        assert_eq!(navigate(&Key::Down, 0, 3), Action::Select(1));
+        assert_eq!(navigate(&Key::Char('j'), 2, 3), Action::Select(2));
+        assert_eq!(navigate(&Key::End, 0, 3), Action::Select(2));
+        assert_eq!(navigate(&Key::Char('2'), 0, 3), Action::Select(1));
+        assert_eq!(navigate(&Key::Char('7'), 0, 3), Action::Ignore);
+        assert_eq!(navigate(&Key::Char('\n'), 1, 3), Action::Choose(1));
+        assert_eq!(navigate(&Key::Esc, 1, 3), Action::Cancel);
+    }
+}
@@ -1,11 +1,11 @@
 use crate::{
     builtins::man_pages::*,
-    shell::{flow_control::Function, status::*, Shell},
-    sys, types,
+    shell::{command_hash, flow_control::Function, status::*, Shell},
+    types,
 };
 use small;
 
-use std::{borrow::Cow, env, path::Path};
+use std::{borrow::Cow, path::Path};
 
 pub(crate) fn which(args: &[small::String], shell: &mut Shell) -> Result<i32, ()> {
     if check_help(args, MAN_WHICH) {
@@ -66,6 +66,50 @@ pub(crate) fn find_type(args: &[small::String], shell: &mut Shell) -> Result<i32
     Ok(result)
 }
 
+/// Describes how each of the names given to `command -v` or `command -V` would be executed.
+/// Other invocations of `command` are executed directly by the shell, which bypasses functions
+/// and aliases named like the command.
+pub(crate) fn command(args: &[small::String], shell: &mut Shell) -> i32 {
+    let verbose = match args.get(1).map(|arg| arg.as_str()) {
+        Some("-v") => false,
+        Some("-V") => true,
+        Some(option) if option.starts_with('-') && option != "--" => {
+            eprintln!("ion: command: invalid option: {}", option);
+            return BAD_ARG;
+        }
+        _ => return SUCCESS,
+    };
+
+    let mut result = SUCCESS;
+    for command in &args[2..] {
+        match get_command_info(command, shell) {
+            Ok(c_type) => match (c_type.as_ref(), verbose) {
+                ("alias", _) => {
+                    if let Some(alias) = shell.variables.get::<types::Alias>(&**command) {
+                        if verbose {
+                            println!("{} is aliased to `{}`", command, &*alias);
+                        } else {
+                            println!("alias {} = '{}'", command, &*alias);
+                        }
+                    }
+                }
+                ("function", true) => println!("{} is a function", command),
+                ("builtin", true) => println!("{} is a shell builtin", command),
+                ("function", false) | ("builtin", false) => println!("{}", command),
+                (path, true) => println!("{} is {}", command, path),
+                (path, false) => println!("{}", path),
+            },
+            Err(_) => {
+                if verbose {
+                    eprintln!("ion: command: {}: not found", command);
+                }
+                result = FAILURE;
+            }
+        }
+    }
+    result
+}
+
 pub(crate) fn get_command_info<'a>(command: &str, shell: &mut Shell) -> Result<Cow<'a, str>, ()> {
     if shell.variables.get::<types::Alias>(command).is_some() {
         return Ok("alias".into());
@@ -73,15 +117,12 @@ pub(crate) fn get_command_info<'a>(command: &str, shell: &mut Shell) -> Result<C
         return Ok("function".into());
     } else if shell.builtins.contains_key(command) {
         return Ok("builtin".into());
-    } else {
-        for path in
-            env::var("PATH").unwrap_or_else(|_| String::from("/bin")).split(sys::PATH_SEPARATOR)
-        {
-            let executable = Path::new(path).join(command);
-            if executable.is_file() {
-                return Ok(executable.display().to_string().into());
-            }
+    } else if command.contains('/') {
+        if command_hash::is_executable(Path::new(command)) {
+            return Ok(command.to_owned().into());
         }
+    } else if let Some(path) = shell.command_path(command) {
+        return Ok(path.display().to_string().into());
     }
     Err(())
 }
@@ -1,10 +1,16 @@
 use crate::{
     builtins::man_pages::{check_help, MAN_EXEC},
-    shell::Shell,
-    sys::execve,
+    parser::pipelines::{PipeItem, Pipeline, RedirectFrom},
+    shell::{JobKind, Shell},
+    sys::{self, execve},
 };
 use small;
-use std::error::Error;
+use std::{
+    error::Error,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    os::unix::io::{AsRawFd, RawFd},
+};
 
 /// Executes the givent commmand.
 pub(crate) fn exec(shell: &mut Shell, args: &[small::String]) -> Result<(), small::String> {
@@ -26,9 +32,80 @@ pub(crate) fn exec(shell: &mut Shell, args: &[small::String]) -> Result<(), smal
     match args.get(idx) {
         Some(argument) => {
             let args = if args.len() > idx + 1 { &args[idx + 1..] } else { &[] };
+            let path = shell.command_hash.hit(argument, &shell.get_str_or_empty("PATH"));
             shell.prep_for_exit();
-            Err(execve(argument, args, (flags & CLEAR_ENV) == 1).description().into())
+            let path = path.as_ref().and_then(|path| path.to_str());
+            Err(execve(argument, path, args, (flags & CLEAR_ENV) == 1).description().into())
         }
         None => Err("no command provided".into()),
     }
 }
+
+/// Determines if the pipeline is an `exec` which was given redirections but no command, as in
+/// `exec &> build.log`, or a descriptor number to redirect, as in `exec 3< input`.
+pub(crate) fn redirects_shell(pipeline: &Pipeline) -> bool {
+    match pipeline.items.as_slice() {
+        [item] => {
+            item.job.builtin.is_some()
+                && item.job.kind == JobKind::Last
+                && item.job.args[0].as_str() == "exec"
+                && item.job.args.len() <= 2
+                && item.job.args.get(1).map_or(true, |fd| fd.parse::<RawFd>().is_ok())
+                && !(item.inputs.is_empty() && item.outputs.is_empty())
+        }
+        _ => false,
+    }
+}
+
+/// Replaces a descriptor of the shell with the given file.
+fn replace(file: &File, fd: RawFd) -> Result<(), String> {
+    // Output which was written before the descriptor is replaced belongs to the old file.
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+    sys::dup2(file.as_raw_fd(), fd)
+        .map(|_| ())
+        .map_err(|why| format!("could not redirect descriptor {}: {}", fd, why))
+}
+
+/// Applies the redirections of an `exec` which was given no command to the shell itself, so
+/// that they remain in effect for every command which follows. If a descriptor number was
+/// given, each redirection is applied to that descriptor rather than to a standard stream.
+///
+/// Descriptors from `SHELL_FD_MIN` upwards are held by the shell itself, and are refused.
+pub(crate) fn redirect_shell(item: &mut PipeItem) -> Result<(), String> {
+    let target = match item.job.args.get(1) {
+        Some(fd) => match fd.parse::<RawFd>() {
+            Ok(fd) if fd >= sys::SHELL_FD_MIN => {
+                return Err(format!("{}: descriptor is reserved by the shell", fd));
+            }
+            Ok(fd) if fd >= 0 => Some(fd),
+            _ => return Err(format!("{}: not a descriptor", fd)),
+        },
+        None => None,
+    };
+
+    for input in &mut item.inputs {
+        // The reason for the failure has already been reported.
+        let file = input.get_infile().ok_or_else(String::new)?;
+        replace(&file, target.unwrap_or(sys::STDIN_FILENO))?;
+    }
+
+    for output in &item.outputs {
+        let file = if output.append {
+            OpenOptions::new().create(true).write(true).append(true).open(output.file.as_str())
+        } else {
+            File::create(output.file.as_str())
+        };
+        let file = file.map_err(|why| format!("{}: {}", output.file, why))?;
+        match (target, output.from) {
+            (Some(fd), _) => replace(&file, fd)?,
+            (None, RedirectFrom::Stdout) => replace(&file, sys::STDOUT_FILENO)?,
+            (None, RedirectFrom::Stderr) => replace(&file, sys::STDERR_FILENO)?,
+            (None, RedirectFrom::Both) => {
+                replace(&file, sys::STDOUT_FILENO)?;
+                replace(&file, sys::STDERR_FILENO)?;
+            }
+        }
+    }
+    Ok(())
+}
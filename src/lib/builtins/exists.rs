@@ -1,4 +1,4 @@
-use std::{fs, os::unix::fs::PermissionsExt};
+use std::fs;
 
 #[cfg(test)]
 use crate::shell::{self, flow_control::Statement};
@@ -75,42 +75,10 @@ fn path_is_directory(filepath: &str) -> bool {
     fs::metadata(filepath).ok().map_or(false, |metadata| metadata.file_type().is_dir())
 }
 
-/// Returns true if the binary is found in path (and is executable)
+/// Returns true if the binary is found in path (and is executable). The binary is found
+/// through the command hash, which is shared with the execution of external commands.
 fn binary_is_in_path(binaryname: &str, shell: &Shell) -> bool {
-    // TODO: Maybe this function should reflect the logic for spawning new processes
-    // TODO: Right now they use an entirely different logic which means that it
-    // *might* be possible TODO: that `exists` reports a binary to be in the
-    // path, while the shell cannot find it or TODO: vice-versa
-    if let Some(path) = shell.get::<types::Str>("PATH") {
-        for fname in path.split(':').map(|dir| format!("{}/{}", dir, binaryname)) {
-            if let Ok(metadata) = fs::metadata(&fname) {
-                if metadata.is_file() && file_has_execute_permission(&fname) {
-                    return true;
-                }
-            }
-        }
-    };
-
-    false
-}
-
-/// Returns true if the file has execute permissions. This function is rather low level because
-/// Rust currently does not have a higher level abstraction for obtaining non-standard file modes.
-/// To extract the permissions from the mode, the bitwise AND operator will be used and compared
-/// with the respective execute bits.
-/// Note: This function is 1:1 the same as src/builtins/test.rs:file_has_execute_permission
-/// If you change the following function, please also update the one in src/builtins/test.rs
-fn file_has_execute_permission(filepath: &str) -> bool {
-    const USER: u32 = 0b100_0000;
-    const GROUP: u32 = 0b1000;
-    const GUEST: u32 = 0b1;
-
-    // Collect the mode of permissions for the file
-    fs::metadata(filepath)
-        .map(|metadata| metadata.permissions().mode())
-        .ok()
-        // If the mode is equal to any of the above, return `SUCCESS`
-        .map_or(false, |mode| mode & (USER + GROUP + GUEST) != 0)
+    shell.command_path(binaryname).is_some()
 }
 
 /// Returns true if the string is not empty
@@ -290,14 +258,6 @@ fn test_binary_is_in_path() {
     assert_eq!(binary_is_in_path("file_does_not_exist", &shell), false);
 }
 
-#[test]
-fn test_file_has_execute_permission() {
-    assert_eq!(file_has_execute_permission("testing/executable_file"), true);
-    assert_eq!(file_has_execute_permission("testing"), true);
-    assert_eq!(file_has_execute_permission("testing/empty_file"), false);
-    assert_eq!(file_has_execute_permission("this-does-not-exist"), false);
-}
-
 #[test]
 fn test_string_is_nonzero() {
     assert_eq!(string_is_nonzero("NOT ZERO"), true);
@@ -0,0 +1,69 @@
+//! Contains the `hash` builtin, which displays and manages the locations of the external commands
+//! that the shell has found within the `PATH`.
+
+use crate::shell::{status::*, Shell};
+use small;
+use std::path::PathBuf;
+
+/// Finds each of the named commands, remembering where they were found. If no names are given,
+/// the commands which have been found are listed with the number of times they were executed.
+/// `-r` forgets every command, and `-p PATH NAME` remembers that `NAME` is found at `PATH`.
+pub(crate) fn hash(args: &[small::String], shell: &mut Shell) -> i32 {
+    let search = shell.get_str_or_empty("PATH");
+    let mut names = args;
+    while let Some(option) = names.get(0) {
+        match option.as_str() {
+            "-r" => {
+                shell.command_hash.clear();
+                names = &names[1..];
+            }
+            "-p" => match (names.get(1), names.get(2)) {
+                (Some(path), Some(name)) if !name.is_empty() && !name.contains('/') => {
+                    shell.command_hash.remember(name, PathBuf::from(path.as_str()), &search);
+                    names = &names[3..];
+                }
+                (Some(_), Some(name)) => {
+                    eprintln!("ion: hash: {}: invalid command name", name);
+                    return BAD_ARG;
+                }
+                _ => {
+                    eprintln!("ion: hash: -p requires a path and a name");
+                    return BAD_ARG;
+                }
+            },
+            "--" => {
+                names = &names[1..];
+                break;
+            }
+            option if option.starts_with('-') => {
+                eprintln!("ion: hash: invalid option: {}", option);
+                return BAD_ARG;
+            }
+            _ => break,
+        }
+    }
+
+    if args.is_empty() {
+        let entries = shell.command_hash.entries(&search);
+        if !entries.is_empty() {
+            println!("hits\tcommand");
+            for (_, hashed) in entries {
+                println!("{:4}\t{}", hashed.hits, hashed.path.display());
+            }
+        }
+        return SUCCESS;
+    }
+
+    let mut status = SUCCESS;
+    for name in names {
+        // Builtins are never searched for, as they are executed before any external command.
+        if shell.builtins.contains_key(name) {
+            continue;
+        }
+        if shell.command_path(name).is_none() {
+            eprintln!("ion: hash: {}: not found", name);
+            status = FAILURE;
+        }
+    }
+    status
+}
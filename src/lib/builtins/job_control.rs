@@ -1,14 +1,93 @@
-//! Contains the `jobs`, `disown`, `bg`, and `fg` commands that manage job
-//! control in the shell.
-
-use crate::shell::{
-    job_control::{JobControl, ProcessState},
-    signals,
-    status::*,
-    Shell,
+//! Contains the `jobs`, `disown`, `bg`, `fg`, `wait`, `kill`, and `coproc` commands that manage
+//! job control in the shell.
+
+use crate::{
+    shell::{
+        job_control::{BackgroundProcess, JobControl, ProcessState},
+        pipe_exec,
+        reaper,
+        signals,
+        status::*,
+        Shell,
+    },
+    sys,
+    types,
 };
 use small;
 use smallvec::SmallVec;
+use std::os::unix::io::AsRawFd;
+
+/// Resolves a job spec to the ID of the job that it refers to.
+///
+/// A job is referred to by its ID, either as `n` or `%n`; as `%%`, `%+`, or `%` for the current
+/// job; as `%-` for the previous job; as `%name` for the job whose command begins with `name`;
+/// or as `%?text` for the job whose command contains `text`.
+pub(crate) fn job_spec(shell: &Shell, spec: &str) -> Result<u32, String> {
+    resolve(&shell.background.lock().unwrap(), shell.previous_job, shell.prior_job, spec)
+}
+
+fn resolve(
+    jobs: &[BackgroundProcess],
+    current: u32,
+    previous: u32,
+    spec: &str,
+) -> Result<u32, String> {
+    let exists =
+        |id: u32| jobs.get(id as usize).map_or(false, |job| job.state != ProcessState::Empty);
+
+    let (prefixed, pattern) =
+        if spec.starts_with('%') { (true, &spec[1..]) } else { (false, spec) };
+
+    match pattern {
+        "" | "%" | "+" if prefixed => {
+            if exists(current) {
+                Ok(current)
+            } else {
+                Err("no current job".to_owned())
+            }
+        }
+        // If there is no previous job, the current job is used instead.
+        "-" if prefixed => match (exists(previous), exists(current)) {
+            (true, _) => Ok(previous),
+            (false, true) => Ok(current),
+            (false, false) => Err("no previous job".to_owned()),
+        },
+        _ if !pattern.is_empty() && pattern.bytes().all(|byte| byte.is_ascii_digit()) => {
+            match pattern.parse::<u32>() {
+                Ok(id) if exists(id) => Ok(id),
+                _ => Err(format!("{}: no such job", spec)),
+            }
+        }
+        _ if !prefixed => Err(format!("{}: invalid job spec", spec)),
+        _ if pattern.starts_with('?') => {
+            find(jobs, spec, |command| command.contains(&pattern[1..]))
+        }
+        _ => find(jobs, spec, |command| command.starts_with(pattern)),
+    }
+}
+
+/// Finds the only job whose command satisfies the predicate.
+fn find(
+    jobs: &[BackgroundProcess],
+    spec: &str,
+    predicate: impl Fn(&str) -> bool,
+) -> Result<u32, String> {
+    let matches: Vec<u32> = jobs
+        .iter()
+        .enumerate()
+        .filter(|(_, job)| job.state != ProcessState::Empty && predicate(&job.name))
+        .map(|(id, _)| id as u32)
+        .collect();
+
+    match matches.len() {
+        0 => Err(format!("{}: no such job", spec)),
+        1 => Ok(matches[0]),
+        _ => {
+            let ids: Vec<String> = matches.iter().map(|id| id.to_string()).collect();
+            Err(format!("{}: ambiguous job spec, matches jobs {}", spec, ids.join(", ")))
+        }
+    }
+}
 
 /// Disowns given process job IDs, and optionally marks jobs to not receive SIGHUP signals.
 /// The `-a` flag selects all jobs, `-r` selects all running jobs, and `-h` specifies to mark
@@ -29,15 +108,11 @@ pub(crate) fn disown(shell: &mut Shell, args: &[small::String]) -> Result<(), St
             "-a" => flags |= ALL_JOBS,
             "-h" => flags |= NO_SIGHUP,
             "-r" => flags |= RUN_JOBS,
-            _ => {
-                let jobspec =
-                    arg.parse::<u32>().map_err(|_| format!("invalid jobspec: '{}'", arg))?;
-                collected_jobs.push(jobspec);
-            }
+            _ => collected_jobs.push(job_spec(shell, arg)?),
         }
     }
 
-    if flags == 0 {
+    if flags == 0 && collected_jobs.is_empty() {
         return Err("must provide arguments".to_owned());
     } else if (flags & ALL_JOBS) == 0 && collected_jobs.is_empty() {
         return Err("must provide a jobspec with -h or -r".to_owned());
@@ -81,13 +156,136 @@ pub(crate) fn disown(shell: &mut Shell, args: &[small::String]) -> Result<(), St
     Ok(())
 }
 
-/// Display a list of all jobs running in the background.
-pub(crate) fn jobs(shell: &mut Shell) {
-    for (id, process) in shell.background.lock().unwrap().iter().enumerate() {
-        if process.state != ProcessState::Empty {
+/// Quotes the string as a JSON string.
+fn json_string(string: &str) -> String {
+    let mut quoted = String::with_capacity(string.len() + 2);
+    quoted.push('"');
+    for character in string.chars() {
+        match character {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            _ if character.is_control() => {
+                quoted.push_str(&format!("\\u{:04x}", character as u32))
+            }
+            _ => quoted.push(character),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Formats the jobs as a JSON array, so that they may be read by other programs.
+fn jobs_json(jobs: &[(usize, &BackgroundProcess)]) -> String {
+    let jobs: Vec<String> = jobs
+        .iter()
+        .map(|&(id, process)| {
+            let pids: Vec<String> = process.members.iter().map(|pid| pid.to_string()).collect();
+            format!(
+                "{{\"id\":{},\"pgid\":{},\"state\":\"{}\",\"command\":{},\"pids\":[{}]}}",
+                id,
+                process.pid,
+                process.state.to_string().to_lowercase(),
+                json_string(&process.name),
+                pids.join(",")
+            )
+        })
+        .collect();
+    ["[", &jobs.join(","), "]"].concat()
+}
+
+/// Display a list of all jobs running in the background, or only the jobs which were given.
+///
+/// The `-l` flag also lists the process IDs of each job's members, `-p` lists only the process
+/// group IDs of the jobs, `-r` and `-s` select only running or stopped jobs, and `--json` prints
+/// the jobs as a JSON array.
+pub(crate) fn jobs(shell: &mut Shell, args: &[small::String]) -> i32 {
+    // Lists the process IDs of the members of each job.
+    const LONG: u8 = 1;
+    // Lists only the process group IDs of the jobs.
+    const PGIDS: u8 = 2;
+    // Selects running jobs.
+    const RUNNING: u8 = 4;
+    // Selects stopped jobs.
+    const STOPPED: u8 = 8;
+    // Prints the jobs as JSON.
+    const JSON: u8 = 16;
+
+    let mut flags = 0u8;
+    let mut status = SUCCESS;
+    let mut given = false;
+    let mut selected: SmallVec<[u32; 16]> = SmallVec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--json" => flags |= JSON,
+            options if options.starts_with('-') && options.len() > 1 => {
+                for option in options.bytes().skip(1) {
+                    flags |= match option {
+                        b'l' => LONG,
+                        b'p' => PGIDS,
+                        b'r' => RUNNING,
+                        b's' => STOPPED,
+                        _ => {
+                            eprintln!("ion: jobs: invalid option: -{}", option as char);
+                            return BAD_ARG;
+                        }
+                    };
+                }
+            }
+            spec => {
+                given = true;
+                match job_spec(shell, spec) {
+                    Ok(njob) => selected.push(njob),
+                    Err(why) => {
+                        eprintln!("ion: jobs: {}", why);
+                        status = FAILURE;
+                    }
+                }
+            }
+        }
+    }
+
+    if given && selected.is_empty() {
+        return status;
+    }
+
+    if flags & (LONG | JSON) != 0 {
+        reaper::collect_members();
+    }
+
+    let processes = shell.background.lock().unwrap();
+    let jobs: Vec<(usize, &BackgroundProcess)> = processes
+        .iter()
+        .enumerate()
+        .filter(|&(id, process)| {
+            let state = match process.state {
+                ProcessState::Running => flags & STOPPED == 0 || flags & RUNNING != 0,
+                ProcessState::Stopped => flags & RUNNING == 0 || flags & STOPPED != 0,
+                ProcessState::Empty => false,
+            };
+            state && (selected.is_empty() || selected.contains(&(id as u32)))
+        })
+        .collect();
+
+    if flags & JSON != 0 {
+        println!("{}", jobs_json(&jobs));
+    } else if flags & PGIDS != 0 {
+        for (_, process) in jobs {
+            println!("{}", process.pid);
+        }
+    } else {
+        for (id, process) in jobs {
             eprintln!("[{}] {} {}\t{}", id, process.pid, process.state, process.name);
+            if flags & LONG != 0 {
+                for pid in &process.members {
+                    eprintln!("    {}", pid);
+                }
+            }
         }
     }
+    status
 }
 
 /// Hands control of the foreground process to the specified jobs, recording their exit status.
@@ -129,10 +327,10 @@ pub(crate) fn fg(shell: &mut Shell, args: &[small::String]) -> i32 {
         }
     } else {
         for arg in args {
-            match arg.parse::<u32>() {
+            match job_spec(shell, arg) {
                 Ok(njob) => status = fg_job(shell, njob),
-                Err(_) => {
-                    eprintln!("ion: fg: {} is not a valid job number", arg);
+                Err(why) => {
+                    eprintln!("ion: fg: {}", why);
                     status = FAILURE;
                 }
             }
@@ -174,11 +372,12 @@ pub(crate) fn bg(shell: &mut Shell, args: &[small::String]) -> i32 {
         }
     } else {
         for arg in args {
-            error = if let Ok(njob) = arg.parse::<u32>() {
-                bg_job(shell, njob)
-            } else {
-                eprintln!("ion: bg: {} is not a valid job number", arg);
-                true
+            error = match job_spec(shell, arg) {
+                Ok(njob) => bg_job(shell, njob),
+                Err(why) => {
+                    eprintln!("ion: bg: {}", why);
+                    true
+                }
             };
         }
     }
@@ -188,3 +387,332 @@ pub(crate) fn bg(shell: &mut Shell, args: &[small::String]) -> i32 {
         SUCCESS
     }
 }
+
+/// Resolves an argument of `wait`, which is either a job spec or the process ID of a job.
+///
+/// Jobs which have finished may still be given by their ID or process ID, until their exit
+/// status has been collected.
+fn wait_target(shell: &Shell, arg: &str) -> Result<u32, String> {
+    let finished = |process: &BackgroundProcess| process.status.is_some();
+
+    if arg.starts_with('%') {
+        if let Ok(njob) = arg[1..].parse::<u32>() {
+            if shell.background.lock().unwrap().get(njob as usize).map_or(false, finished) {
+                return Ok(njob);
+            }
+        }
+        return job_spec(shell, arg);
+    }
+
+    let pid = arg.parse::<u32>().map_err(|_| format!("{}: not a process ID or job spec", arg))?;
+    shell
+        .background
+        .lock()
+        .unwrap()
+        .iter()
+        .position(|process| {
+            process.pid == pid && (process.state != ProcessState::Empty || finished(process))
+        })
+        .map(|njob| njob as u32)
+        .ok_or_else(|| format!("pid {} is not a child of this shell", pid))
+}
+
+/// Takes the exit status of a job which has finished.
+fn collect_status(shell: &Shell, njob: u32) -> i32 {
+    shell.background.lock().unwrap()[njob as usize].status.take().unwrap_or(SUCCESS)
+}
+
+/// Waits for the given jobs to finish, or for every background job if no jobs were given,
+/// returning the exit status of the last job given.
+///
+/// With `-n`, only the first of the jobs to finish is waited for, and its exit status is
+/// returned instead.
+pub(crate) fn wait(shell: &mut Shell, args: &[small::String]) -> i32 {
+    let (any, args) = match args.split_first() {
+        Some((flag, args)) if flag == "-n" => (true, args),
+        _ => (false, args),
+    };
+
+    if args.is_empty() && !any {
+        shell.wait_for_background();
+        for process in shell.background.lock().unwrap().iter_mut() {
+            process.status = None;
+        }
+        return SUCCESS;
+    }
+
+    let mut status = SUCCESS;
+    let mut jobs: SmallVec<[u32; 16]> = SmallVec::new();
+    for arg in args {
+        match wait_target(shell, arg) {
+            Ok(njob) if any => jobs.push(njob),
+            Ok(njob) => {
+                shell.wait_for_any(&[njob]);
+                status = collect_status(shell, njob);
+            }
+            Err(why) => {
+                eprintln!("ion: wait: {}", why);
+                status = NO_SUCH_COMMAND;
+            }
+        }
+    }
+
+    if !any {
+        return status;
+    }
+
+    if args.is_empty() {
+        let processes = shell.background.lock().unwrap();
+        jobs.extend(
+            processes
+                .iter()
+                .enumerate()
+                .filter(|(_, process)| process.state != ProcessState::Empty)
+                .map(|(njob, _)| njob as u32),
+        );
+    }
+
+    if jobs.is_empty() {
+        return NO_SUCH_COMMAND;
+    }
+
+    let njob = shell.wait_for_any(&jobs);
+    collect_status(shell, njob)
+}
+
+/// Lists the names of every signal, or converts each argument between a signal's name and
+/// number. Exit statuses of processes that were terminated by a signal are also accepted.
+fn list_signals(args: &[small::String]) -> i32 {
+    if args.is_empty() {
+        for &(name, signal) in sys::SIGNALS {
+            println!("{:2}) SIG{}", signal, name);
+        }
+        return SUCCESS;
+    }
+
+    let mut status = SUCCESS;
+    for arg in args {
+        match arg.parse::<i32>() {
+            Ok(number) => match signals::name(number & 0x7f) {
+                Some(name) => println!("{}", name),
+                None => {
+                    eprintln!("ion: kill: {}: invalid signal specification", arg);
+                    status = FAILURE;
+                }
+            },
+            Err(_) => match signals::from_name(arg) {
+                Some(signal) => println!("{}", signal),
+                None => {
+                    eprintln!("ion: kill: {}: invalid signal specification", arg);
+                    status = FAILURE;
+                }
+            },
+        }
+    }
+    status
+}
+
+/// Sends a signal to each of the given processes, process groups, and jobs.
+///
+/// The signal defaults to `SIGTERM`, and may be given by name or number with `-s SIG` or
+/// `-SIG`. Jobs are referred to with `%` job specs, and receive the signal as a process group.
+pub(crate) fn kill(shell: &mut Shell, args: &[small::String]) -> i32 {
+    let mut signal = sys::SIGTERM;
+    let mut args = args;
+
+    match args.first().map(|arg| arg.as_str()) {
+        Some("-l") | Some("-L") => return list_signals(&args[1..]),
+        Some("-s") | Some("-n") => {
+            let name = args.get(1).map_or("", |arg| arg.as_str());
+            match signals::from_name(name) {
+                Some(sig) => signal = sig,
+                None => {
+                    eprintln!("ion: kill: {}: invalid signal specification", name);
+                    return BAD_ARG;
+                }
+            }
+            args = &args[2..];
+        }
+        // Process groups must be given after `--`, as `-NUM` refers to a signal number.
+        Some(arg) if arg.starts_with('-') && arg != "--" => {
+            match signals::from_name(&arg[1..]) {
+                Some(sig) => signal = sig,
+                None => {
+                    eprintln!("ion: kill: {}: invalid signal specification", &arg[1..]);
+                    return BAD_ARG;
+                }
+            }
+            args = &args[1..];
+        }
+        _ => (),
+    }
+
+    if args.first().map_or(false, |arg| arg == "--") {
+        args = &args[1..];
+    }
+
+    if args.is_empty() {
+        eprintln!("ion: kill: usage: kill [-s SIGNAL | -SIGNAL] PID | %JOB...");
+        return BAD_ARG;
+    }
+
+    let mut status = SUCCESS;
+    for target in args {
+        let result = if target.starts_with('%') {
+            let pgid = job_spec(shell, target)
+                .map(|njob| shell.background.lock().unwrap()[njob as usize].pid);
+            match pgid {
+                // Stopped jobs must be continued in order to handle the signal.
+                Ok(pgid) => sys::killpg(pgid, signal).and_then(|_| {
+                    if signal == sys::SIGTERM || signal == sys::SIGHUP {
+                        sys::killpg(pgid, sys::SIGCONT)
+                    } else {
+                        Ok(())
+                    }
+                }),
+                Err(why) => {
+                    eprintln!("ion: kill: {}", why);
+                    status = FAILURE;
+                    continue;
+                }
+            }
+        } else {
+            match target.parse::<i32>() {
+                // Negative IDs refer to process groups.
+                Ok(pid) if pid < 0 => match pid.checked_neg() {
+                    Some(pgid) => sys::killpg(pgid as u32, signal),
+                    None => {
+                        eprintln!("ion: kill: {}: invalid process group", target);
+                        status = FAILURE;
+                        continue;
+                    }
+                },
+                Ok(pid) => sys::kill(pid as u32, signal),
+                Err(_) => {
+                    eprintln!("ion: kill: {}: arguments must be process or job IDs", target);
+                    status = FAILURE;
+                    continue;
+                }
+            }
+        };
+
+        if let Err(why) = result {
+            eprintln!("ion: kill: ({}) - {}", target, why);
+            status = FAILURE;
+        }
+    }
+    status
+}
+
+/// Starts a coprocess, or closes the input of the given coprocesses with `-c`.
+///
+/// The paths through which the shell reads from and writes to the coprocess are stored in the
+/// `NAME` array, and the process ID of its job in the `NAME_PID` variable.
+pub(crate) fn coproc(shell: &mut Shell, args: &[small::String]) -> i32 {
+    match args.get(0).map(|arg| arg.as_str()) {
+        Some("-c") => {
+            let mut status = SUCCESS;
+            for name in &args[1..] {
+                match shell.coprocesses.get_mut(name.as_str()) {
+                    Some(coprocess) => coprocess.input = None,
+                    None => {
+                        eprintln!("ion: coproc: {}: no such coprocess", name);
+                        status = FAILURE;
+                    }
+                }
+            }
+            status
+        }
+        Some(name) if args.len() > 1 => {
+            if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                eprintln!("ion: coproc: {}: invalid coprocess name", name);
+                return BAD_ARG;
+            }
+
+            let pid = match pipe_exec::fork_coprocess(shell, name, &args[1..]) {
+                Ok(pid) => pid,
+                Err(why) => {
+                    eprintln!("ion: coproc: failed to start {}: {}", name, why);
+                    return FAILURE;
+                }
+            };
+
+            let paths: types::Array = {
+                let coprocess = &shell.coprocesses[name];
+                let input = coprocess.input.as_ref().map_or(-1, |input| input.as_raw_fd());
+                [coprocess.output.as_raw_fd(), input]
+                    .iter()
+                    .map(|fd| format!("/dev/fd/{}", fd).into())
+                    .collect()
+            };
+            shell.variables.set(name, paths);
+            shell.variables.set(&[name, "_PID"].concat(), pid.to_string());
+            SUCCESS
+        }
+        _ => {
+            eprintln!("ion: coproc: expected a name and a command");
+            BAD_ARG
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(name: &str, state: ProcessState) -> BackgroundProcess {
+        BackgroundProcess {
+            pid: 1,
+            ignore_sighup: false,
+            state,
+            name: name.into(),
+            members: Vec::new(),
+            status: None,
+        }
+    }
+
+    #[test]
+    fn job_specs() {
+        let jobs = vec![
+            job("sleep 100", ProcessState::Running),
+            job("cargo build", ProcessState::Empty),
+            job("cargo test", ProcessState::Stopped),
+            job("vim notes", ProcessState::Running),
+        ];
+
+        assert_eq!(resolve(&jobs, 3, 0, "%%"), Ok(3));
+        assert_eq!(resolve(&jobs, 3, 0, "%+"), Ok(3));
+        assert_eq!(resolve(&jobs, 3, 0, "%"), Ok(3));
+        assert_eq!(resolve(&jobs, 3, 0, "%-"), Ok(0));
+        assert_eq!(resolve(&jobs, 3, 1, "%-"), Ok(3));
+        assert_eq!(resolve(&jobs, 1, !0, "%%"), Err("no current job".into()));
+        assert_eq!(resolve(&jobs, 3, 0, "2"), Ok(2));
+        assert_eq!(resolve(&jobs, 3, 0, "%2"), Ok(2));
+        assert_eq!(resolve(&jobs, 3, 0, "%1"), Err("%1: no such job".into()));
+        assert_eq!(resolve(&jobs, 3, 0, "%7"), Err("%7: no such job".into()));
+        assert_eq!(resolve(&jobs, 3, 0, "%vim"), Ok(3));
+        assert_eq!(resolve(&jobs, 3, 0, "%cargo"), Ok(2));
+        assert_eq!(resolve(&jobs, 3, 0, "%?100"), Ok(0));
+        assert_eq!(resolve(&jobs, 3, 0, "%ls"), Err("%ls: no such job".into()));
+        assert_eq!(resolve(&jobs, 3, 0, "vim"), Err("vim: invalid job spec".into()));
+        assert_eq!(
+            resolve(&jobs, 3, 0, "%?e"),
+            Err("%?e: ambiguous job spec, matches jobs 0, 2, 3".into())
+        );
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(json_string("echo \"a\tb\"\n"), r#""echo \"a\tb\"\n""#);
+
+        let mut sleep = job("sleep 100 | cat", ProcessState::Running);
+        sleep.members = vec![12, 13];
+        let stopped = job("vim", ProcessState::Stopped);
+        assert_eq!(
+            jobs_json(&[(0, &sleep), (2, &stopped)]),
+            r#"[{"id":0,"pgid":1,"state":"running","command":"sleep 100 | cat","pids":[12,13]},"#
+                .to_owned()
+                + r#"{"id":2,"pgid":1,"state":"stopped","command":"vim","pids":[]}]"#
+        );
+    }
+}
@@ -0,0 +1,265 @@
+//! Contains the `ulimit` and `umask` builtins, which manage the resource limits and the file
+//! mode creation mask of the shell, and of the commands that it executes.
+
+use crate::{shell::status::*, sys};
+use small;
+
+/// The resources managed by `ulimit`: the option which selects each resource, its description,
+/// and the size in bytes of the units in which its limit is given.
+const RESOURCES: &[(char, &str, i32, u64)] = &[
+    ('c', "core file size (blocks)", sys::RLIMIT_CORE, 512),
+    ('d', "data seg size (kbytes)", sys::RLIMIT_DATA, 1024),
+    ('f', "file size (blocks)", sys::RLIMIT_FSIZE, 512),
+    ('l', "max locked memory (kbytes)", sys::RLIMIT_MEMLOCK, 1024),
+    ('m', "max memory size (kbytes)", sys::RLIMIT_RSS, 1024),
+    ('n', "open files", sys::RLIMIT_NOFILE, 1),
+    ('s', "stack size (kbytes)", sys::RLIMIT_STACK, 1024),
+    ('t', "cpu time (seconds)", sys::RLIMIT_CPU, 1),
+    ('u', "max user processes", sys::RLIMIT_NPROC, 1),
+    ('v', "virtual memory (kbytes)", sys::RLIMIT_AS, 1024),
+];
+
+fn format_limit(limit: u64, unit: u64) -> String {
+    if limit == sys::RLIM_INFINITY {
+        "unlimited".into()
+    } else {
+        (limit / unit).to_string()
+    }
+}
+
+/// Displays or sets the soft and hard limits of resources.
+pub(crate) fn ulimit(args: &[small::String]) -> i32 {
+    const HARD: u8 = 1;
+    const SOFT: u8 = 2;
+
+    let mut all = false;
+    let mut which = 0u8;
+    let mut selected = Vec::new();
+    let mut value = None;
+    for arg in args {
+        if arg.starts_with('-') && arg.len() > 1 {
+            for option in arg.chars().skip(1) {
+                match option {
+                    'a' => all = true,
+                    'H' => which |= HARD,
+                    'S' => which |= SOFT,
+                    _ => match RESOURCES.iter().find(|resource| resource.0 == option) {
+                        Some(resource) => selected.push(resource),
+                        None => {
+                            eprintln!("ion: ulimit: invalid option: -{}", option);
+                            return BAD_ARG;
+                        }
+                    },
+                }
+            }
+        } else if value.is_none() {
+            value = Some(arg.as_str());
+        } else {
+            eprintln!("ion: ulimit: too many arguments");
+            return BAD_ARG;
+        }
+    }
+
+    if all {
+        selected = RESOURCES.iter().collect();
+    } else if selected.is_empty() {
+        selected.push(&RESOURCES[2]);
+    }
+
+    let mut status = SUCCESS;
+    for &&(option, description, resource, unit) in &selected {
+        let (soft, hard) = match sys::getrlimit(resource) {
+            Ok(limits) => limits,
+            Err(why) => {
+                eprintln!("ion: ulimit: {}: cannot get limit: {}", description, why);
+                status = FAILURE;
+                continue;
+            }
+        };
+
+        match value {
+            Some(value) => {
+                let limit = if value == "unlimited" {
+                    Some(sys::RLIM_INFINITY)
+                } else {
+                    value.parse::<u64>().ok().and_then(|limit| limit.checked_mul(unit))
+                };
+
+                let limit = match limit {
+                    Some(limit) => limit,
+                    None => {
+                        eprintln!("ion: ulimit: {}: invalid limit", value);
+                        return BAD_ARG;
+                    }
+                };
+
+                // Without -H or -S, both limits are set.
+                let soft = if which & HARD == 0 || which & SOFT != 0 { limit } else { soft };
+                let hard = if which & SOFT == 0 || which & HARD != 0 { limit } else { hard };
+                if let Err(why) = sys::setrlimit(resource, soft, hard) {
+                    eprintln!("ion: ulimit: {}: cannot modify limit: {}", description, why);
+                    status = FAILURE;
+                }
+            }
+            None => {
+                // The soft limit is displayed unless -H was given.
+                let limit = format_limit(if which & HARD != 0 { hard } else { soft }, unit);
+                if selected.len() == 1 {
+                    println!("{}", limit);
+                } else {
+                    println!("{:<28} (-{}) {}", description, option, limit);
+                }
+            }
+        }
+    }
+    status
+}
+
+/// Applies a symbolic mode, such as `u=rwx,g+r,o-w`, to the permissions which are allowed by a
+/// file mode creation mask, returning the permissions which result.
+fn apply_symbolic(mode: &str, mut permissions: u32) -> Option<u32> {
+    for clause in mode.split(',') {
+        let mut chars = clause.chars().peekable();
+
+        let mut who = 0;
+        while let Some(&character) = chars.peek() {
+            who |= match character {
+                'u' => 0o700,
+                'g' => 0o070,
+                'o' => 0o007,
+                'a' => 0o777,
+                _ => break,
+            };
+            chars.next();
+        }
+        if who == 0 {
+            who = 0o777;
+        }
+
+        // Each clause contains at least one operation, such as `+r`, `-w`, or `=rx`.
+        let mut operations = 0;
+        while let Some(operator) = chars.next() {
+            let mut bits = 0;
+            while let Some(&character) = chars.peek() {
+                bits |= match character {
+                    'r' => 0o444,
+                    'w' => 0o222,
+                    'x' => 0o111,
+                    _ => break,
+                };
+                chars.next();
+            }
+
+            bits &= who;
+            permissions = match operator {
+                '+' => permissions | bits,
+                '-' => permissions & !bits,
+                '=' => (permissions & !who) | bits,
+                _ => return None,
+            };
+            operations += 1;
+        }
+
+        if operations == 0 {
+            return None;
+        }
+    }
+
+    Some(permissions)
+}
+
+/// Formats the permissions which are allowed by a file mode creation mask, as in `u=rwx,g=rx,o=`.
+fn format_symbolic(mask: u32) -> String {
+    let permissions = !mask & 0o777;
+    ["u", "g", "o"]
+        .iter()
+        .enumerate()
+        .map(|(index, who)| {
+            let bits = (permissions >> (6 - 3 * index)) & 0o7;
+            let mut clause = format!("{}=", who);
+            for &(bit, character) in &[(0o4, 'r'), (0o2, 'w'), (0o1, 'x')] {
+                if bits & bit != 0 {
+                    clause.push(character);
+                }
+            }
+            clause
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Displays or sets the file mode creation mask. The mask may be given in octal, or as a
+/// symbolic mode describing the permissions which the mask allows.
+pub(crate) fn umask(args: &[small::String]) -> i32 {
+    let (symbolic, mode) = match args.get(0).map(|arg| arg.as_str()) {
+        Some("-S") => (true, args.get(1)),
+        _ => (false, args.get(0)),
+    };
+
+    // The mask may only be read by replacing it, after which it is restored.
+    let current = match sys::umask(0) {
+        Ok(mask) => {
+            let _ = sys::umask(mask);
+            mask
+        }
+        Err(why) => {
+            eprintln!("ion: umask: {}", why);
+            return FAILURE;
+        }
+    };
+
+    let mode = match mode {
+        Some(mode) => mode,
+        None => {
+            if symbolic {
+                println!("{}", format_symbolic(current));
+            } else {
+                println!("{:04o}", current);
+            }
+            return SUCCESS;
+        }
+    };
+
+    let mask = if mode.chars().all(|character| character.is_digit(8)) {
+        u32::from_str_radix(mode, 8).ok().filter(|&mask| mask <= 0o777)
+    } else {
+        apply_symbolic(mode, !current & 0o777).map(|permissions| !permissions & 0o777)
+    };
+
+    match mask {
+        Some(mask) => match sys::umask(mask) {
+            Ok(_) => SUCCESS,
+            Err(why) => {
+                eprintln!("ion: umask: {}", why);
+                FAILURE
+            }
+        },
+        None => {
+            eprintln!("ion: umask: {}: invalid mode", mode);
+            BAD_ARG
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbolic_modes() {
+        assert_eq!(apply_symbolic("u=rwx,g=rx,o=", 0), Some(0o750));
+        assert_eq!(apply_symbolic("go-w", 0o777), Some(0o755));
+        assert_eq!(apply_symbolic("a+r", 0o700), Some(0o744));
+        assert_eq!(apply_symbolic("+x", 0o644), Some(0o755));
+        assert_eq!(apply_symbolic("u+r-w", 0o600), Some(0o400));
+        assert_eq!(apply_symbolic("u", 0o600), None);
+        assert_eq!(apply_symbolic("u*r", 0o600), None);
+    }
+
+    #[test]
+    fn symbolic_masks() {
+        assert_eq!(format_symbolic(0o022), "u=rwx,g=rx,o=rx");
+        assert_eq!(format_symbolic(0o077), "u=rwx,g=,o=");
+        assert_eq!(format_symbolic(0o000), "u=rwx,g=rwx,o=rwx");
+    }
+}
@@ -70,6 +70,36 @@ SYNOPSIS
 DESCRIPTION
     Returns 0 exit status if the supplied file descriptor is a tty."#;
 
+pub(crate) const MAN_COPROC: &str = r#"NAME
+    coproc - execute a command with pipes to its input and output
+
+SYNOPSIS
+    coproc NAME COMMAND [ARGS...]
+    coproc -c NAME...
+
+DESCRIPTION
+    Executes the command as a background job, named the coprocess NAME, whose standard input
+    and output are connected to pipes held by the shell. The path through which the output of
+    the coprocess is read is stored in @NAME[0], and the path through which its input is
+    written is stored in @NAME[1]. The process ID of the job is stored in $NAME_PID.
+
+    The paths may only be opened by the shell, in redirections and builtins. External commands
+    do not inherit the pipes, so they are connected to a coprocess by redirecting their input
+    or output, as in `cat < @NAME[0]`, rather than by being given a path.
+
+    Starting another coprocess with the same name closes the pipes of the former coprocess.
+
+OPTIONS
+    -c
+        Closes the input of each of the given coprocesses, so that they read the end of the
+        file.
+
+EXAMPLES
+    coproc calc bc -l
+    echo '2 / 3' > @calc[1]
+    read result < @calc[0]
+"#;
+
 pub(crate) const MAN_DIRS: &str = r#"NAME
     dirs - prints the directory stack
 
@@ -127,13 +157,47 @@ DESCRIPTION
 //"#;
 
 pub(crate) const MAN_READ: &str = r#"NAME
-    read - read a line of input into some variables
+    read - read records of input into some variables
 
 SYNOPSIS
-    read VARIABLES...
+    read [-s] [-p PROMPT] [-n COUNT] [-d DELIMITER] [-t DURATION] [-u FD] [VARIABLES...]
+    read [-s] [-p PROMPT] [-n COUNT] [-d DELIMITER] [-t DURATION] [-u FD] -a ARRAY
 
 DESCRIPTION
-    For each variable reads from standard input and stores the results in the variable."#;
+    For each variable, reads a record from standard input and stores it in the variable, with
+    its surrounding whitespace removed. A record ends with a newline, unless another delimiter
+    is given. Variables may be the keys of maps, or the indices of arrays, as in map[key]. If no
+    variables are given, the record is stored in REPLY.
+
+    When the input is a terminal, and none of -s, -n, -d, or -t are given, each line is read
+    with line editing, after prompting with the name of its variable.
+
+    The exit status is 1 if the input ended before each record was read, in which case the
+    partial record is still stored, and 142 if the input timed out.
+
+OPTIONS
+    -a ARRAY
+        read a single record, and store the words within it as the elements of the array
+    -d DELIMITER
+        end each record with the first character of DELIMITER, rather than a newline
+    -n COUNT
+        end each record after COUNT characters, keeping its surrounding whitespace
+    -p PROMPT
+        display the prompt on standard error before reading, if the input is a terminal
+    -s
+        do not echo the input, if the input is a terminal
+    -t DURATION
+        fail if the input is not read within the duration, as given to the sleep builtin; a
+        duration of 0 reads nothing, and succeeds only if input is available to be read
+    -u FD
+        read from the file descriptor, rather than from standard input
+
+EXAMPLES
+    read -s -p "password: " password
+    read -n 1 -p "continue? [y/n] " answer
+    read -d , first second <<< "one,two"
+    read -a words <<< "one two three"
+"#;
 
 pub(crate) const MAN_DROP: &str = r#"NAME
     drop - delete some variables or arrays
@@ -153,12 +217,16 @@ pub(crate) const MAN_SET: &str = r#"NAME
     set - Set or unset values of shell options and positional parameters.
 
 SYNOPSIS
-    set [ --help ] [-e | +e] [-x | +x] [-o [vi | emacs]] [- | --] [STRING]...
+    set [ --help ] [-b | +b] [-e | +e] [-x | +x] [-o [vi | emacs | huponexit | notify]]
+        [- | --] [STRING]...
 
 DESCRIPTION
     Shell options may be set using the '-' character, and unset using the '+' character.
 
 OPTIONS
+    -b  Report changes in the state of background jobs immediately, rather than before the
+        next prompt. This is the same as `-o notify`.
+
     -e  Exit immediately if a command exits with a non-zero status.
 
     -o  Specifies that an argument will follow that sets the key map.
@@ -200,15 +268,46 @@ pub(crate) const MAN_EXEC: &str = r#"NAME
 
 SYNOPSIS
     exec [-ch] [--help] [command [arguments ...]]
+    exec [descriptor] redirections...
 
 DESCRIPTION
     Execute <command>, replacing the shell with the specified program.
     The <arguments> following the command become the arguments to
     <command>.
 
+    If no command is given, the redirections are instead applied to the
+    shell itself, and remain in effect for every command which follows.
+    `exec &> build.log` sends both the standard output and error of the
+    rest of the script to build.log. If a descriptor number is given, the
+    redirections are applied to that descriptor, so `exec 3< input` opens
+    input for reading as descriptor 3. Descriptors from 10 upwards are held
+    by the shell itself, and may not be redirected.
+
 OPTIONS
     -c  Execute command with an empty environment."#;
 
+pub(crate) const MAN_HASH: &str = r#"NAME
+    hash - display or reset the locations of commands
+
+SYNOPSIS
+    hash [-r] [-p PATH NAME] [NAME...]
+
+DESCRIPTION
+    The shell remembers where each external command was found within the PATH
+    the first time that it is executed, so that the PATH is not searched again.
+    The commands are forgotten whenever PATH is assigned, even to its own value.
+
+    With no arguments, lists the commands which have been found, and the number
+    of times that each has been executed. Otherwise, each NAME is searched for
+    and remembered, and the status is 1 if any of them could not be found.
+
+OPTIONS
+    -r
+        Forgets the locations of every command.
+
+    -p PATH NAME
+        Remembers that the command NAME is found at PATH."#;
+
 pub(crate) const MAN_HISTORY: &str = r#"NAME
     history - print command history
 
@@ -258,6 +357,110 @@ OPTIONS
         \t  horizontal tab (HT)
         \v  vertical tab (VT)"#;
 
+pub(crate) const MAN_ARGPARSE: &str = r#"NAME
+    argparse - parse the options given to a script or function
+
+SYNOPSIS
+    argparse [-n NAME] SPECS... -- ARGUMENTS...
+
+DESCRIPTION
+    Parses the arguments which follow the --, according to the options which are specified
+    before it. Short options may be combined, as in -vq, and values may be given to options as
+    -o VALUE, -oVALUE, --output VALUE, or --output=VALUE. Options and positional arguments may
+    be given in any order, until a -- is given, after which every argument is positional.
+
+    Each option is stored in a variable named after its long name, or its short name if it has
+    none, prefixed with flag_ and with dashes replaced by underscores. Flags are set to true or
+    false, and options which take a value are set to the value which was given, or are removed
+    if none was. Repeated options are set to an array of their values. The positional arguments
+    which remain are stored in the args array.
+
+    Unless the specification defines -h or --help, they display help text which is generated
+    from the specification, set flag_help to true, and return an exit status of 3, so that
+    argparse ... || exit stops a script once its help was displayed.
+
+    Unknown options, and options which are missing their values, are reported on standard error,
+    with the name given to -n, and an exit status of 1 is returned.
+
+SPECIFICATIONS
+    v/verbose
+        a flag with a short and a long name, stored in flag_verbose
+    v
+        a flag with only a short name, stored in flag_v
+    dry-run
+        a flag with only a long name, stored in flag_dry_run
+    o/output=
+        an option which requires a value
+    level=?
+        an option which may be given a value, as --level=VALUE or -lVALUE
+    I/include=+
+        an option which requires a value, and which may be repeated
+
+EXAMPLES
+    fn build args:[str]
+        argparse -n build r/release j/jobs= F/feature=+ -- @args || return
+        if test $flag_help = true
+            return
+        end
+        echo release: $flag_release, features: @flag_feature, targets: @args
+    end
+"#;
+
+pub(crate) const MAN_PRINTF: &str = r#"NAME
+    printf - format and print data
+
+SYNOPSIS
+    printf [-v VARIABLE] FORMAT [ARGUMENTS...]
+
+DESCRIPTION
+    Writes the format to standard output, replacing each conversion specification within it
+    with the next argument, formatted according to the specification. If arguments remain once
+    the format has been written, it is written again for them, and conversions which lack an
+    argument are given an empty string, or zero. Arguments which are not valid numbers are
+    formatted as zero, and cause an exit status of 1.
+
+    A specification is written as %[FLAGS][WIDTH][.PRECISION]CONVERSION. The width and the
+    precision may be given as *, in which case they are taken from the next argument. As in C,
+    neither may exceed 2147483647.
+
+OPTIONS
+    -v VARIABLE
+        assign the output to the variable, rather than printing it
+
+CONVERSIONS
+    %s  the argument as a string, of at most PRECISION characters
+    %b  the argument, with its backslash escapes interpreted, where \0NNN is an octal value
+    %q  the argument, quoted so that it may be reused as input to the shell
+    %c  the first character of the argument
+    %d  a signed decimal integer, as is %i
+    %u  an unsigned decimal integer
+    %o  an unsigned octal integer
+    %x  an unsigned hexadecimal integer, using capital letters with %X
+    %f  a floating point number, with PRECISION decimal places
+    %e  a floating point number in scientific notation, using E with %E
+    %g  a floating point number in %f or %e notation, with PRECISION significant digits
+    %%  a literal %
+
+    Integers may be given in hexadecimal with a leading 0x, in octal with a leading 0, or as the
+    character code of the character which follows a leading quote.
+
+FLAGS
+    -    align to the left of the width
+    +    prefix positive numbers with a plus sign
+    ' '  prefix positive numbers with a space
+    #    use an alternate form: prefix octal with 0 and hexadecimal with 0x
+    0    pad numbers with zeroes rather than spaces
+
+ESCAPE SEQUENCES
+    The format may contain the escape sequences of echo -e, along with \", \', \NNN for octal
+    values, \xHH for hexadecimal values, and \uHHHH and \UHHHHHHHH for unicode characters.
+
+EXAMPLES
+    printf "%-10s|%5.2f\n" total 3.14159
+    printf "%s=%d\n" one 1 two 2
+    printf -v hex "%04x" 255
+"#;
+
 pub(crate) const MAN_RANDOM: &str = r#"NAME
     random - generate a random number
 
@@ -292,28 +495,91 @@ pub(crate) const MAN_JOBS: &str = r#"NAME
     jobs - list all jobs running in the background
 
 SYNOPSIS
-    jobs
+    jobs [-lprs] [--json] [JOB...]
+
+DESCRIPTION
+    Prints a list of all jobs running in the background, or of only the jobs given.
+
+OPTIONS
+    -l
+        Also lists the process IDs of every process which is still running in each job.
+
+    -p
+        Lists only the process group ID of each job.
+
+    -r
+        Lists only the running jobs.
+
+    -s
+        Lists only the stopped jobs.
+
+    --json
+        Prints the jobs as a JSON array of objects, with the id, pgid, state, command, and pids
+        of each job.
+
+JOB SPECS
+    A job may be referred to by its ID, as N or %N; as %%, %+, or % for the current job; as %-
+    for the previous job; as %NAME for the job whose command begins with NAME; or as %?TEXT for
+    the job whose command contains TEXT."#;
+
+pub(crate) const MAN_KILL: &str = r#"NAME
+    kill - send a signal to processes and jobs
+
+SYNOPSIS
+    kill [-s SIGNAL | -SIGNAL] [--] PID | %JOB...
+    kill -l [SIGNAL | STATUS]...
 
 DESCRIPTION
-    Prints a list of all jobs running in the background."#;
+    Sends a signal, which is SIGTERM by default, to each of the given targets. A target is
+    either a process ID, a negative process group ID given after --, or a job spec beginning
+    with %, such as %N for job N or %% for the current job. The signal is sent to every process
+    in the job. Stopped jobs are also continued after receiving SIGTERM or SIGHUP.
+
+    Signals may be given by name, with or without the SIG prefix, or by number.
+
+OPTIONS
+    -s SIGNAL, -n SIGNAL, -SIGNAL
+        Specifies the signal to send.
+
+    -l, -L
+        Lists every signal, or converts each argument between a signal's name and number. An
+        exit status of a process that was terminated by a signal is converted to its name.
+
+EXAMPLES
+    kill %1
+    kill -s KILL 1234
+    kill -HUP %%
+    kill -l 137"#;
 
 pub(crate) const MAN_BG: &str = r#"NAME
     bg - sends jobs to background
 
 SYNOPSIS
-    bg PID
+    bg [JOB...]
 
 DESCRIPTION
-    bg sends the job to the background resuming it if it has stopped."#;
+    bg sends the job to the background resuming it if it has stopped. If no job is given, the
+    current job is resumed.
+
+JOB SPECS
+    A job may be referred to by its ID, as N or %N; as %%, %+, or % for the current job; as %-
+    for the previous job; as %NAME for the job whose command begins with NAME; or as %?TEXT for
+    the job whose command contains TEXT."#;
 
 pub(crate) const MAN_FG: &str = r#"NAME
     fg - bring job to foreground
 
 SYNOPSIS
-    fg PID
+    fg [JOB...]
 
 DESCRIPTION
-    fg brings the specified job to foreground resuming it if it has stopped."#;
+    fg brings the specified job to foreground resuming it if it has stopped. If no job is given,
+    the current job is brought to the foreground.
+
+JOB SPECS
+    A job may be referred to by its ID, as N or %N; as %%, %+, or % for the current job; as %-
+    for the previous job; as %NAME for the job whose command begins with NAME; or as %?TEXT for
+    the job whose command contains TEXT."#;
 
 pub(crate) const MAN_SUSPEND: &str = r#"NAME
     suspend - suspend the current shell
@@ -329,7 +595,7 @@ pub(crate) const MAN_DISOWN: &str = r#"NAME
     disown - Disown processes
 
 SYNOPSIS
-    disown [ --help | -r | -h | -a ][PID...]
+    disown [ --help | -r | -h | -a ][JOB...]
 
 DESCRIPTION
     Disowning a process removes that process from the shell's background process table.
@@ -337,7 +603,57 @@ DESCRIPTION
 OPTIONS
     -r  Remove all running jobs from the background process list.
     -h  Specifies that each job supplied will not receive the SIGHUP signal when the shell receives a SIGHUP.
-    -a  If no job IDs were supplied, remove all jobs from the background process list."#;
+    -a  If no job IDs were supplied, remove all jobs from the background process list.
+
+JOB SPECS
+    A job may be referred to by its ID, as N or %N; as %%, %+, or % for the current job; as %-
+    for the previous job; as %NAME for the job whose command begins with NAME; or as %?TEXT for
+    the job whose command contains TEXT."#;
+
+pub(crate) const MAN_WAIT: &str = r#"NAME
+    wait - wait for background jobs to finish
+
+SYNOPSIS
+    wait [-n] [PID | %JOB...]
+
+DESCRIPTION
+    Waits for each of the given jobs to finish, and returns the exit status of the last job
+    given. Jobs are given either as process IDs or as job specs beginning with %. A job which
+    has already finished may still be waited for, until its exit status has been collected by
+    wait, or in an interactive shell, until its completion has been reported. If no jobs were
+    given, every background job is waited for, and wait returns 0.
+
+    If a job does not exist, or is not a child of the shell, the exit status is 127.
+
+OPTIONS
+    -n
+        Waits only for the first of the given jobs to finish, or for the first of every
+        background job if none were given, and returns its exit status.
+
+JOB SPECS
+    A job may be referred to as %N for job N; as %%, %+, or % for the current job; as %- for
+    the previous job; as %NAME for the job whose command begins with NAME; or as %?TEXT for the
+    job whose command contains TEXT."#;
+
+pub(crate) const MAN_ABBR: &str = r#"NAME
+    abbr - View, set or erase abbreviations
+
+SYNOPSIS
+    abbr [ -h | --help ] [ -s | --save ] [ -e | --erase ] [NAME [EXPANSION...]]
+
+DESCRIPTION
+    Abbreviations are expanded within the line editor when space or enter is pressed after one
+    has been typed in the position of a command, so that the history records the command which
+    was actually executed.
+
+    Without arguments, all abbreviations are listed in a form that may be evaluated again. With
+    only a name, the expansion of that abbreviation is printed.
+
+OPTIONS
+    -e, --erase
+        Erase each of the given abbreviations.
+    -s, --save
+        Also record the change in the init file, so that it persists across sessions."#;
 
 pub(crate) const MAN_EXIT: &str = r#"NAME
     exit - exit the shell
@@ -429,3 +745,169 @@ SYNOPSIS
 DESCRIPTION
     The which utility takes a list of command names and searches for the
     alias/builtin/function/executable that would be executed if you ran that command."#;
+
+pub(crate) const MAN_COMMAND: &str = r#"NAME
+    command - execute a command, bypassing functions and aliases
+
+SYNOPSIS
+    command [--] COMMAND [ARGUMENTS...]
+    command -v | -V NAME...
+
+DESCRIPTION
+    Executes the given builtin or external command, even if a function or alias
+    has the same name. This allows a function which wraps a program to execute
+    the program that it is named after, as in `fn ls dir; command ls -F $dir; end`.
+
+OPTIONS
+    -v
+        Prints how each name would be executed: the path of an external command,
+        the name of a builtin or function, or the definition of an alias.
+
+    -V
+        Prints a description of how each name would be executed.
+
+    The status is 1 if any of the names could not be found."#;
+
+pub(crate) const MAN_BUILTIN: &str = r#"NAME
+    builtin - execute a builtin, bypassing functions and aliases
+
+SYNOPSIS
+    builtin [--] BUILTIN [ARGUMENTS...]
+
+DESCRIPTION
+    Executes the given builtin, even if a function or alias has the same name,
+    as in `fn cd path; builtin cd $path && ls; end`. The status is 1 if
+    there is no builtin with the given name."#;
+
+pub(crate) const MAN_SLEEP: &str = r#"NAME
+    sleep - delay for an amount of time
+
+SYNOPSIS
+    sleep DURATION...
+
+DESCRIPTION
+    Delays for the sum of the given durations. A duration is a number of seconds, which may be
+    fractional, and which may be followed by a unit of ms, s, m, h, or d. A duration of
+    infinity, or inf, sleeps until the sleep is interrupted.
+
+    The sleep is interrupted if the shell receives a signal."#;
+
+pub(crate) const MAN_TIMEOUT: &str = r#"NAME
+    timeout - terminate a command which runs for too long
+
+SYNOPSIS
+    timeout [-s SIGNAL] [-k DURATION] DURATION COMMAND [ARGS...]
+
+DESCRIPTION
+    Executes the command, which may be an external command, a builtin, or a function, within a
+    process group of its own. If the command runs for longer than the given duration, every
+    process in the group is sent a signal, which is SIGTERM by default. Durations are given as
+    for the sleep builtin.
+
+    Blocks such as if or for cannot be given as the command. To limit the time that a block
+    takes, define a function which executes the block, and give that function instead.
+
+    The exit status of the command is returned, or 124 if the command ran for too long. If the
+    command had to be killed with SIGKILL, 137 is returned instead.
+
+OPTIONS
+    -s SIGNAL
+        The signal to send when the command runs for too long, by name or number.
+
+    -k DURATION
+        If the command is still running for this long after the signal was sent, it is killed
+        with SIGKILL.
+
+EXAMPLES
+    timeout 1.5 curl https://example.com
+    timeout -k 5s 1m cargo test
+"#;
+
+pub(crate) const MAN_TIMES: &str = r#"NAME
+    times - display the CPU times consumed by the shell
+
+SYNOPSIS
+    times
+
+DESCRIPTION
+    Displays the user and system CPU times consumed by the shell on the first line, followed by
+    those consumed by the commands that the shell has executed and waited upon on the second.
+    Times are given in minutes and seconds, as in 0m1.250s.
+"#;
+
+pub(crate) const MAN_ULIMIT: &str = r#"NAME
+    ulimit - display or set resource limits
+
+SYNOPSIS
+    ulimit [-a] [-H | -S] [-cdflmnstuv] [LIMIT | unlimited]
+
+DESCRIPTION
+    Displays or sets the limits of the resources which are available to the shell, and to the
+    commands that it executes. Without a resource option, the file size limit is used. When a
+    limit is given, both the soft and hard limits are set, unless -H or -S was given; otherwise
+    the soft limit is displayed, unless -H was given.
+
+OPTIONS
+    -a  display every limit
+    -H  the hard limit
+    -S  the soft limit
+    -c  core file size, in blocks of 512 bytes
+    -d  data segment size, in kilobytes
+    -f  file size, in blocks of 512 bytes
+    -l  locked memory size, in kilobytes
+    -m  resident set size, in kilobytes
+    -n  number of open files
+    -s  stack size, in kilobytes
+    -t  CPU time, in seconds
+    -u  number of processes
+    -v  virtual memory size, in kilobytes
+"#;
+
+pub(crate) const MAN_UMASK: &str = r#"NAME
+    umask - display or set the file mode creation mask
+
+SYNOPSIS
+    umask [-S] [MODE]
+
+DESCRIPTION
+    Displays or sets the file mode creation mask, whose permissions are removed from the files
+    which are created by the shell and the commands that it executes. The mask is displayed in
+    octal, or with -S, as the symbolic mode of the permissions which the mask allows.
+
+    A mode is given either in octal, or symbolically as the permissions to allow, such as
+    u=rwx,g=rx,o= or go-w.
+"#;
+
+pub(crate) const MAN_CHOOSE: &str = r#"NAME
+    choose - display a menu, and assign the chosen item to a variable
+
+SYNOPSIS
+    choose [-n] [-p PROMPT] [-i INDEX_VARIABLE] [--] VARIABLE ITEM...
+
+DESCRIPTION
+    Displays a menu of the items, and assigns the item which is chosen to VARIABLE,
+    and its index, counting from 0, to INDEX_VARIABLE, which is VARIABLE_index
+    unless another name is given. The menu is written to the standard error.
+
+    When the standard input and standard error are terminals, an item is selected
+    with the arrow keys, j and k, or its number, and chosen with Enter. Escape or
+    q cancels the menu. Otherwise, the items are numbered, and lines are read until
+    one contains the number of an item. An empty line displays the menu again.
+
+    The status is 1 if the menu was cancelled, or the input ended without a choice,
+    and 130 if either menu was interrupted with Ctrl-C.
+
+OPTIONS
+    -n
+        Displays a numbered menu, even on a terminal.
+
+    -p PROMPT
+        The prompt which is displayed with the menu. A numbered menu is otherwise
+        prompted with `#? `.
+
+    -i INDEX_VARIABLE
+        The variable which is assigned the index of the chosen item.
+
+EXAMPLES
+    choose -p 'Pick a fruit:' fruit apple banana cherry
+    echo "$fruit is item $fruit_index""#;
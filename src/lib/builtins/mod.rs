@@ -2,18 +2,27 @@ pub mod functions;
 pub mod source;
 pub mod variables;
 
+mod abbreviations;
+mod argparse;
+mod choose;
 mod command_info;
-mod exec;
+pub(crate) mod exec;
 mod exists;
+mod hash;
 mod is;
 mod job_control;
+mod limits;
 mod man_pages;
+mod read;
 mod set;
 mod status;
+mod timeout;
+pub(crate) mod times;
 
-use ion_builtins::{calc, conditionals, echo, random, test};
+use ion_builtins::{calc, conditionals, echo, printf, random, test};
 
 use self::{
+    abbreviations::abbr,
     command_info::*,
     echo::echo,
     exec::exec,
@@ -36,7 +45,7 @@ use crate::{
     shell::{
         self,
         fork_function::fork_function,
-        job_control::{JobControl, ProcessState},
+        job_control::ProcessState,
         status::*,
         Shell, ShellHistory,
     },
@@ -72,12 +81,18 @@ macro_rules! map {
 
 /// Builtins are in A-Z order.
 pub const BUILTINS: &BuiltinMap = &map!(
+    "abbr" => builtin_abbr : "View, set or erase abbreviations expanded by the line editor",
     "alias" => builtin_alias : "View, set or unset aliases",
+    "argparse" => builtin_argparse : "Parses the options given to a script or function",
     "bg" => builtin_bg : "Resumes a stopped background process",
     "bool" => builtin_bool : "If the value is '1' or 'true', return 0 exit status",
+    "builtin" => builtin_builtin : "Executes a builtin, bypassing functions and aliases",
     "calc" => builtin_calc : "Calculate a mathematical expression",
     "cd" => builtin_cd : "Change the current directory\n    cd <path>",
+    "choose" => builtin_choose : "Displays a menu, and assigns the chosen item to a variable",
+    "command" => builtin_command : "Executes a command, bypassing functions and aliases",
     "contains" => contains : "Evaluates if the supplied argument contains a given string",
+    "coproc" => builtin_coproc : "Starts a command with pipes to its input and output",
     "dirs" => builtin_dirs : "Display the current directory stack",
     "disown" => builtin_disown : DISOWN_DESC,
     "drop" => builtin_drop : "Delete a variable",
@@ -91,24 +106,32 @@ pub const BUILTINS: &BuiltinMap = &map!(
     "false" => builtin_false : "Do nothing, unsuccessfully",
     "fg" => builtin_fg : "Resumes and sets a background process as the active process",
     "fn" => builtin_fn : "Print list of functions",
+    "hash" => builtin_hash : "Displays or resets the locations of commands found in the PATH",
     "help" => builtin_help : HELP_DESC,
     "history" => builtin_history : "Display a log of all commands previously executed",
     "is" => builtin_is : "Simple alternative to == and !=",
     "isatty" => builtin_isatty : "Returns 0 exit status if the supplied FD is a tty",
     "jobs" => builtin_jobs : "Displays all jobs that are attached to the background",
+    "kill" => builtin_kill : "Sends a signal to processes and jobs",
     "matches" => builtin_matches : "Checks if a string matches a given regex",
     "popd" => builtin_popd : "Pop a directory from the stack",
+    "printf" => builtin_printf : "Formats and prints its arguments, or assigns them to a variable",
     "pushd" => builtin_pushd : "Push a directory to the stack",
     "random" => builtin_random : "Outputs a random u64",
     "read" => builtin_read : "Read some variables\n    read <variable>",
     "set" => builtin_set : "Set or unset values of shell options and positional parameters.",
+    "sleep" => builtin_sleep : "Delays for the given amount of time",
     "source" => builtin_source : SOURCE_DESC,
     "starts-with" => starts_with : "Evaluates if the supplied argument starts with a given string",
     "status" => builtin_status : "Evaluates the current runtime status",
     "suspend" => builtin_suspend : "Suspends the shell with a SIGTSTOP signal",
     "test" => builtin_test : "Performs tests on files and text",
+    "timeout" => builtin_timeout : "Terminates a command which runs for too long",
+    "times" => builtin_times : "Displays the CPU times consumed by the shell and its commands",
     "true" => builtin_true : "Do nothing, successfully",
     "type" => builtin_type : "indicates how a command would be interpreted",
+    "ulimit" => builtin_ulimit : "Displays or sets the resource limits of the shell",
+    "umask" => builtin_umask : "Displays or sets the file mode creation mask",
     "unalias" => builtin_unalias : "Delete an alias",
     "wait" => builtin_wait : "Waits until all running background processes have completed",
     "which" => builtin_which : "Shows the full path of commands"
@@ -261,11 +284,28 @@ fn builtin_popd(args: &[small::String], shell: &mut Shell) -> i32 {
     }
 }
 
+fn builtin_abbr(args: &[small::String], shell: &mut Shell) -> i32 {
+    if check_help(args, MAN_ABBR) {
+        return SUCCESS;
+    }
+    abbr(args, shell)
+}
+
 fn builtin_alias(args: &[small::String], shell: &mut Shell) -> i32 {
     let args_str = args[1..].join(" ");
     alias(&mut shell.variables, &args_str)
 }
 
+fn builtin_argparse(args: &[small::String], shell: &mut Shell) -> i32 {
+    // Only the options of argparse itself, rather than the arguments that it parses, may ask
+    // for its manual page.
+    let separator = args.iter().position(|arg| arg == "--").unwrap_or_else(|| args.len());
+    if check_help(&args[..separator], MAN_ARGPARSE) {
+        return SUCCESS;
+    }
+    argparse::argparse(&args[1..], shell)
+}
+
 fn builtin_unalias(args: &[small::String], shell: &mut Shell) -> i32 {
     drop_alias(&mut shell.variables, args)
 }
@@ -278,7 +318,7 @@ fn builtin_read(args: &[small::String], shell: &mut Shell) -> i32 {
     if check_help(args, MAN_READ) {
         return SUCCESS;
     }
-    shell.variables.read(args)
+    read::read(&args[1..], shell)
 }
 
 fn builtin_drop(args: &[small::String], shell: &mut Shell) -> i32 {
@@ -363,6 +403,65 @@ fn builtin_echo(args: &[small::String], _: &mut Shell) -> i32 {
     }
 }
 
+fn builtin_printf(args: &[small::String], shell: &mut Shell) -> i32 {
+    if check_help(args, MAN_PRINTF) {
+        return SUCCESS;
+    }
+
+    let mut args = &args[1..];
+    let mut variable = None;
+    match args.get(0).map(|arg| arg.as_str()) {
+        Some("-v") => match args.get(1) {
+            Some(name) if shell::variables::Variables::is_valid_variable_name(name) => {
+                variable = Some(name);
+                args = &args[2..];
+            }
+            Some(name) => {
+                eprintln!("ion: printf: {}: invalid variable name", name);
+                return BAD_ARG;
+            }
+            None => {
+                eprintln!("ion: printf: -v requires an argument");
+                return BAD_ARG;
+            }
+        },
+        _ => (),
+    }
+    if args.get(0).map_or(false, |arg| arg == "--") {
+        args = &args[1..];
+    }
+
+    let format = match args.get(0) {
+        Some(format) => format,
+        None => {
+            eprintln!("ion: printf: missing format");
+            return BAD_ARG;
+        }
+    };
+
+    let mut output = Vec::new();
+    let mut status = match printf::printf(format, &args[1..], &mut output) {
+        Ok(()) => SUCCESS,
+        Err(why) => {
+            eprintln!("ion: printf: {}", why);
+            FAILURE
+        }
+    };
+
+    match variable {
+        Some(name) => shell.variables.set(name, &*String::from_utf8_lossy(&output)),
+        None => {
+            let stdout = io::stdout();
+            let mut stdout = stdout.lock();
+            if let Err(why) = stdout.write_all(&output).and_then(|_| stdout.flush()) {
+                eprintln!("ion: printf: {}", why);
+                status = FAILURE;
+            }
+        }
+    }
+    status
+}
+
 fn builtin_test(args: &[small::String], _: &mut Shell) -> i32 {
     // Do not use `check_help` for the `test` builtin. The
     // `test` builtin contains a "-h" option.
@@ -412,16 +511,81 @@ fn builtin_false(args: &[small::String], _: &mut Shell) -> i32 {
     FAILURE
 }
 
-// TODO create a manpage
-fn builtin_wait(_: &[small::String], shell: &mut Shell) -> i32 {
-    shell.wait_for_background();
-    SUCCESS
+fn builtin_wait(args: &[small::String], shell: &mut Shell) -> i32 {
+    if check_help(args, MAN_WAIT) {
+        return SUCCESS;
+    }
+    job_control::wait(shell, &args[1..])
 }
 
 fn builtin_jobs(args: &[small::String], shell: &mut Shell) -> i32 {
-    check_help(args, MAN_JOBS);
-    job_control::jobs(shell);
-    SUCCESS
+    if check_help(args, MAN_JOBS) {
+        return SUCCESS;
+    }
+    job_control::jobs(shell, &args[1..])
+}
+
+fn builtin_coproc(args: &[small::String], shell: &mut Shell) -> i32 {
+    if check_help(args, MAN_COPROC) {
+        return SUCCESS;
+    }
+    job_control::coproc(shell, &args[1..])
+}
+
+fn builtin_sleep(args: &[small::String], _: &mut Shell) -> i32 {
+    if check_help(args, MAN_SLEEP) {
+        return SUCCESS;
+    }
+    timeout::sleep(&args[1..])
+}
+
+fn builtin_timeout(args: &[small::String], shell: &mut Shell) -> i32 {
+    if check_help(args, MAN_TIMEOUT) {
+        return SUCCESS;
+    }
+    timeout::timeout(shell, &args[1..])
+}
+
+fn builtin_hash(args: &[small::String], shell: &mut Shell) -> i32 {
+    if check_help(args, MAN_HASH) {
+        return SUCCESS;
+    }
+    hash::hash(&args[1..], shell)
+}
+
+fn builtin_choose(args: &[small::String], shell: &mut Shell) -> i32 {
+    if check_help(args, MAN_CHOOSE) {
+        return SUCCESS;
+    }
+    choose::choose(&args[1..], shell)
+}
+
+fn builtin_times(args: &[small::String], _: &mut Shell) -> i32 {
+    if check_help(args, MAN_TIMES) {
+        return SUCCESS;
+    }
+    times::times(&args[1..])
+}
+
+fn builtin_ulimit(args: &[small::String], _: &mut Shell) -> i32 {
+    if check_help(args, MAN_ULIMIT) {
+        return SUCCESS;
+    }
+    limits::ulimit(&args[1..])
+}
+
+fn builtin_umask(args: &[small::String], _: &mut Shell) -> i32 {
+    if check_help(args, MAN_UMASK) {
+        return SUCCESS;
+    }
+    limits::umask(&args[1..])
+}
+
+fn builtin_kill(args: &[small::String], shell: &mut Shell) -> i32 {
+    if check_help(args, MAN_KILL) {
+        return SUCCESS;
+    }
+    job_control::kill(shell, &args[1..])
 }
 
 fn builtin_bg(args: &[small::String], shell: &mut Shell) -> i32 {
@@ -492,6 +656,12 @@ fn builtin_exit(args: &[small::String], shell: &mut Shell) -> i32 {
     if check_help(args, MAN_EXIT) {
         return SUCCESS;
     }
+    let status = args.get(1).and_then(|status| status.parse::<i32>().ok());
+    // Within a prompt function, only the function is ended.
+    if shell.generating_prompt {
+        shell.break_flow = true;
+        return status.unwrap_or(shell.previous_status);
+    }
     // Kill all active background tasks before exiting the shell.
     for process in shell.background.lock().unwrap().iter() {
         if process.state != ProcessState::Empty {
@@ -499,7 +669,7 @@ fn builtin_exit(args: &[small::String], shell: &mut Shell) -> i32 {
         }
     }
     let previous_status = shell.previous_status;
-    shell.exit(args.get(1).and_then(|status| status.parse::<i32>().ok()).unwrap_or(previous_status))
+    shell.exit(status.unwrap_or(previous_status))
 }
 
 fn builtin_exec(args: &[small::String], shell: &mut Shell) -> i32 {
@@ -573,6 +743,33 @@ fn builtin_type(args: &[small::String], shell: &mut Shell) -> i32 {
     }
 }
 
+fn builtin_command(args: &[small::String], shell: &mut Shell) -> i32 {
+    if check_help(args, MAN_COMMAND) {
+        return SUCCESS;
+    }
+    command(args, shell)
+}
+
+fn builtin_builtin(args: &[small::String], shell: &mut Shell) -> i32 {
+    // Builtins which are named when the job is parsed are executed directly, so only the options
+    // which precede the name are for this builtin.
+    if check_help(&args[..args.len().min(2)], MAN_BUILTIN) {
+        return SUCCESS;
+    }
+
+    let args = if args.get(1).map_or(false, |arg| arg == "--") { &args[2..] } else { &args[1..] };
+    match args.get(0) {
+        Some(name) => match shell.builtins.get(name) {
+            Some(builtin) => (builtin.main)(args, shell),
+            None => {
+                eprintln!("ion: builtin: {}: not a shell builtin", name);
+                FAILURE
+            }
+        },
+        None => SUCCESS,
+    }
+}
+
 fn builtin_isatty(args: &[small::String], _: &mut Shell) -> i32 {
     if check_help(args, MAN_ISATTY) {
         return SUCCESS;
@@ -605,3 +802,19 @@ fn builtin_isatty(args: &[small::String], _: &mut Shell) -> i32 {
 
     FAILURE
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtins_are_sorted() {
+        // Builtins are found with a binary search, which requires their names to be sorted.
+        for names in BUILTINS.keys().windows(2) {
+            assert!(names[0] < names[1], "{} must be listed before {}", names[1], names[0]);
+        }
+        for name in BUILTINS.keys() {
+            assert!(BUILTINS.get(name).is_some(), "{} was not found", name);
+        }
+    }
+}
@@ -0,0 +1,304 @@
+//! Contains the `read` builtin, which reads records of input into variables.
+
+use super::timeout::{deadline_after, interrupted, parse_duration};
+use crate::{
+    lexers::assignments::KeyIterator,
+    shell::{
+        signals,
+        status::*,
+        variables::{Value, Variables},
+        Shell,
+    },
+    sys, types,
+};
+use liner::Context;
+use small;
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    mem::ManuallyDrop,
+    os::unix::io::{FromRawFd, RawFd},
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+/// The exit status of a read which timed out, which is that of a command killed by `SIGALRM`.
+const TIMED_OUT: i32 = 142;
+
+/// How a record of input came to an end.
+pub(crate) enum Ending {
+    Delimiter,
+    EndOfFile,
+    TimedOut,
+    Interrupted,
+    Failed(io::Error),
+}
+
+struct Options<'a> {
+    prompt:    Option<&'a str>,
+    silent:    bool,
+    count:     Option<usize>,
+    delimiter: u8,
+    timeout:   Option<Duration>,
+    array:     Option<&'a str>,
+    fd:        RawFd,
+}
+
+/// Waits until input is available to be read from the file descriptor, returning how the
+/// record came to an end if the wait ended otherwise. A terminal is waited for with a poll, which
+/// unlike a read is not restarted once an interrupt has been handled, so that Ctrl-C cancels it.
+pub(crate) fn wait_for_input(fd: RawFd, deadline: Option<Instant>) -> Option<Ending> {
+    let terminal = sys::isatty(fd);
+    loop {
+        let timeout = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Some(Ending::TimedOut);
+                }
+                deadline - now
+            }
+            None if terminal => Duration::from_secs(1),
+            None => return None,
+        };
+
+        match sys::poll_input(fd, timeout) {
+            Ok(true) => return None,
+            Ok(false) if deadline.is_some() => return Some(Ending::TimedOut),
+            // A signal which arrived between polls is noticed on the next timeout.
+            Ok(false) => (),
+            Err(ref why) if why.kind() == io::ErrorKind::Interrupted => (),
+            Err(why) if deadline.is_some() => return Some(Ending::Failed(why)),
+            // Platforms which are unable to poll the terminal read from it directly.
+            Err(_) => return None,
+        }
+        if signals::PENDING.load(Ordering::SeqCst) != 0 {
+            return Some(Ending::Interrupted);
+        }
+    }
+}
+
+/// Reads bytes from the file descriptor until the delimiter is read, the given number of
+/// characters have been read, or the deadline passes. Input is read a byte at a time, so that
+/// input which follows the record remains to be read by other commands.
+pub(crate) fn read_record(
+    fd: RawFd,
+    delimiter: u8,
+    count: Option<usize>,
+    deadline: Option<Instant>,
+) -> (Vec<u8>, Ending) {
+    let mut input = ManuallyDrop::new(unsafe { File::from_raw_fd(fd) });
+    let mut record = Vec::new();
+    let mut characters = 0;
+    let mut continuation = 0u8;
+    loop {
+        if continuation == 0 && count.map_or(false, |count| characters >= count) {
+            return (record, Ending::Delimiter);
+        }
+
+        if let Some(ending) = wait_for_input(fd, deadline) {
+            return (record, ending);
+        }
+
+        let mut byte = [0];
+        match input.read(&mut byte) {
+            Ok(0) => return (record, Ending::EndOfFile),
+            Ok(_) if byte[0] == delimiter => return (record, Ending::Delimiter),
+            Ok(_) => {
+                // Multi-byte characters are counted once all of their bytes have been read.
+                continuation = match byte[0] {
+                    0x80..=0xBF => continuation.saturating_sub(1),
+                    0xC0..=0xDF => 1,
+                    0xE0..=0xEF => 2,
+                    0xF0..=0xF7 => 3,
+                    _ => 0,
+                };
+                if continuation == 0 {
+                    characters += 1;
+                }
+                record.push(byte[0]);
+            }
+            Err(ref why) if why.kind() == io::ErrorKind::Interrupted => {
+                if signals::PENDING.load(Ordering::SeqCst) != 0 {
+                    return (record, Ending::Interrupted);
+                }
+            }
+            Err(why) => return (record, Ending::Failed(why)),
+        }
+    }
+}
+
+/// Assigns a record to a variable, which may be the key of a map or the index of an array.
+fn assign(shell: &mut Shell, name: &str, record: &str) -> Result<(), String> {
+    match KeyIterator::new(name).next() {
+        Some(Ok(ref key)) if Variables::is_valid_variable_name(key.name) => {
+            shell.assign(key, Value::Str(record.into()))
+        }
+        _ => Err(format!("{}: invalid variable name", name)),
+    }
+}
+
+/// Reads a record of input into each of the variables. If an array is given, a single record
+/// is read, and split into the elements of the array by whitespace.
+pub(crate) fn read(args: &[small::String], shell: &mut Shell) -> i32 {
+    let mut options = Options {
+        prompt:    None,
+        silent:    false,
+        count:     None,
+        delimiter: b'\n',
+        timeout:   None,
+        array:     None,
+        fd:        sys::STDIN_FILENO,
+    };
+
+    let mut index = 0;
+    while let Some(arg) = args.get(index) {
+        let option = match arg.as_str() {
+            "--" => {
+                index += 1;
+                break;
+            }
+            "-s" => {
+                options.silent = true;
+                index += 1;
+                continue;
+            }
+            option @ "-p" | option @ "-n" | option @ "-d" | option @ "-t" | option @ "-a"
+            | option @ "-u" => option,
+            option if option.starts_with('-') && option.len() > 1 => {
+                eprintln!("ion: read: invalid option: {}", option);
+                return BAD_ARG;
+            }
+            _ => break,
+        };
+
+        let value = match args.get(index + 1) {
+            Some(value) => value.as_str(),
+            None => {
+                eprintln!("ion: read: {} requires an argument", option);
+                return BAD_ARG;
+            }
+        };
+        let valid = match option {
+            "-p" => {
+                options.prompt = Some(value);
+                true
+            }
+            "-n" => {
+                options.count = value.parse::<usize>().ok();
+                options.count.is_some()
+            }
+            "-d" => {
+                options.delimiter = value.bytes().next().unwrap_or(0);
+                true
+            }
+            "-t" => {
+                options.timeout = parse_duration(value);
+                options.timeout.is_some()
+            }
+            "-a" => {
+                options.array = Some(value);
+                Variables::is_valid_variable_name(value)
+            }
+            _ => {
+                options.fd = value.parse::<RawFd>().unwrap_or(-1);
+                options.fd >= 0
+            }
+        };
+        if !valid {
+            eprintln!("ion: read: {}: invalid argument for {}", value, option);
+            return BAD_ARG;
+        }
+        index += 2;
+    }
+
+    let mut names: Vec<&str> = args[index..].iter().map(|arg| arg.as_str()).collect();
+    if options.array.is_some() && !names.is_empty() {
+        eprintln!("ion: read: variables cannot be given with -a");
+        return BAD_ARG;
+    } else if options.array.is_none() && names.is_empty() {
+        names.push("REPLY");
+    }
+    let targets = options.array.map_or(names.len(), |_| 1);
+
+    if options.timeout == Some(Duration::from_secs(0)) {
+        // As in bash, a timeout of zero only tests whether input is available, without reading.
+        return match sys::poll_input(options.fd, Duration::from_secs(0)) {
+            Ok(true) => SUCCESS,
+            Ok(false) => FAILURE,
+            Err(why) => {
+                eprintln!("ion: read: {}", why);
+                FAILURE
+            }
+        };
+    }
+
+    let terminal = sys::isatty(options.fd);
+    let plain = options.count.is_none() && options.delimiter == b'\n';
+    let mut records = Vec::with_capacity(targets);
+    let mut status = SUCCESS;
+    let editable = options.fd == sys::STDIN_FILENO && !options.silent && options.timeout.is_none();
+    if terminal && plain && editable {
+        // Input from the terminal is read with line editing, prompting for each variable.
+        let mut context = Context::new();
+        for name in options.array.iter().chain(&names).take(targets) {
+            let prompt = options.prompt.map_or_else(|| format!("{}=", name), Into::into);
+            match context.read_line(prompt, None, &mut |_| {}) {
+                Ok(line) => records.push(line.trim().to_owned()),
+                Err(_) => return FAILURE,
+            }
+        }
+    } else {
+        // The terminal's mode is restored once the mode is dropped.
+        let _mode = if terminal && (options.silent || !plain) {
+            sys::terminal_mode(options.fd, !options.silent, plain).ok()
+        } else {
+            None
+        };
+        if let (true, Some(prompt)) = (terminal, options.prompt) {
+            eprint!("{}", prompt);
+            let _ = io::stderr().flush();
+        }
+
+        let deadline = options.timeout.and_then(deadline_after);
+        while records.len() < targets {
+            let (record, ending) =
+                read_record(options.fd, options.delimiter, options.count, deadline);
+            let record = String::from_utf8_lossy(&record);
+            // Whitespace is only significant when a number of characters was requested.
+            let record = if options.count.is_some() { &*record } else { record.trim() };
+            status = match ending {
+                Ending::Delimiter => SUCCESS,
+                Ending::EndOfFile => FAILURE,
+                Ending::TimedOut => TIMED_OUT,
+                Ending::Interrupted => return interrupted(),
+                Ending::Failed(why) => {
+                    eprintln!("ion: read: {}", why);
+                    return FAILURE;
+                }
+            };
+
+            // A partial record is assigned when the input ends, but nothing is read afterwards.
+            records.push(record.to_owned());
+            if status != SUCCESS {
+                break;
+            }
+        }
+    }
+
+    match options.array {
+        Some(array) => {
+            let elements = records.get(0).map_or("", |record| record.as_str()).split_whitespace();
+            shell.variables.set(array, elements.map(Into::into).collect::<types::Array>());
+        }
+        None => {
+            for (name, record) in names.iter().zip(&records) {
+                if let Err(why) = assign(shell, name, record) {
+                    eprintln!("ion: read: {}", why);
+                    return FAILURE;
+                }
+            }
+        }
+    }
+    status
+}
@@ -31,6 +31,7 @@ pub(crate) fn set(args: &[small::String], shell: &mut Shell) -> i32 {
             }
             for flag in arg.bytes().skip(1) {
                 match flag {
+                    b'b' => shell.notifications.set_immediate(true),
                     b'e' => shell.flags |= ERR_EXIT,
                     b'o' => match args_iter.next().map(|s| s as &str) {
                         Some("vi") => {
@@ -44,6 +45,7 @@ pub(crate) fn set(args: &[small::String], shell: &mut Shell) -> i32 {
                             }
                         }
                         Some("huponexit") => shell.flags |= HUPONEXIT,
+                        Some("notify") => shell.notifications.set_immediate(true),
                         Some(_) => {
                             eprintln!("ion: set: invalid option");
                             return 0;
@@ -60,10 +62,12 @@ pub(crate) fn set(args: &[small::String], shell: &mut Shell) -> i32 {
         } else if arg.starts_with('+') {
             for flag in arg.bytes().skip(1) {
                 match flag {
+                    b'b' => shell.notifications.set_immediate(false),
                     b'e' => shell.flags &= 255 ^ ERR_EXIT,
                     b'x' => shell.flags &= 255 ^ PRINT_COMMS,
                     b'o' => match args_iter.next().map(|s| s as &str) {
                         Some("huponexit") => shell.flags &= 255 ^ HUPONEXIT,
+                        Some("notify") => shell.notifications.set_immediate(false),
                         Some(_) => {
                             eprintln!("ion: set: invalid option");
                             return 0;
@@ -0,0 +1,295 @@
+//! Contains the `timeout` and `sleep` builtins, and the parsing of the durations given to them.
+
+use crate::{
+    shell::{pipe_exec, signals, status::*, Shell},
+    sys,
+};
+use small;
+use std::{
+    cmp,
+    sync::atomic::Ordering,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The exit status of a command which was terminated for running past its time limit.
+const TIMED_OUT: i32 = 124;
+
+/// How often a process is checked upon while waiting for a deadline, in milliseconds.
+const INTERVAL: u64 = 10;
+
+/// Parses a number of seconds, which may be fractional, and which may be followed by a unit of
+/// `ms`, `s`, `m`, `h`, or `d`.
+pub(crate) fn parse_duration(text: &str) -> Option<Duration> {
+    let split = text.find(|c: char| c.is_ascii_alphabetic()).unwrap_or_else(|| text.len());
+    let (number, unit) = text.split_at(split);
+    let scale = match unit {
+        "ms" => 0.001,
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        "d" => 86400.0,
+        _ => return None,
+    };
+
+    let seconds = number.parse::<f64>().ok()? * scale;
+    if seconds.is_finite() && seconds >= 0.0 && seconds < u64::max_value() as f64 {
+        Some(Duration::new(seconds.trunc() as u64, (seconds.fract() * 1e9).round() as u32))
+    } else {
+        None
+    }
+}
+
+/// The instant at which the duration from now will have passed, or `None` if that is too far
+/// away to be represented, in which case the duration is treated as never passing.
+pub(crate) fn deadline_after(duration: Duration) -> Option<Instant> {
+    // `Instant::checked_add` is newer than the toolchain which the shell is built with, so
+    // durations which are longer than any that could be waited for are not added at all.
+    const LONGEST: u64 = 100 * 365 * 86400;
+    if duration.as_secs() < LONGEST {
+        Some(Instant::now() + duration)
+    } else {
+        None
+    }
+}
+
+/// The exit status of a builtin which was interrupted by the signal that the shell received.
+pub(crate) fn interrupted() -> i32 {
+    128 + match signals::PENDING.load(Ordering::SeqCst) as u8 {
+        signals::SIGHUP => sys::SIGHUP,
+        signals::SIGTERM => sys::SIGTERM,
+        _ => sys::SIGINT,
+    }
+}
+
+/// Sleeps until the deadline, in short intervals so that the sleep is interrupted if the shell
+/// receives a signal. Without a deadline, the sleep only ends once it is interrupted. Returns
+/// `false` if the sleep was interrupted.
+fn sleep_until(deadline: Option<Instant>) -> bool {
+    let interval = Duration::from_millis(INTERVAL * 10);
+    loop {
+        if signals::PENDING.load(Ordering::SeqCst) != 0 {
+            return false;
+        }
+        match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return true;
+                }
+                thread::sleep(cmp::min(deadline - now, interval));
+            }
+            None => thread::sleep(interval),
+        }
+    }
+}
+
+/// Waits for the process to exit, returning its exit status, or `None` if the deadline passed
+/// before it exited. Without a deadline, the process is waited upon until it exits.
+fn wait_until(pid: u32, deadline: Option<Instant>) -> Option<i32> {
+    loop {
+        let mut status = 0;
+        let options = if deadline.is_some() { sys::WNOHANG } else { 0 };
+        match sys::waitpid(pid as i32, &mut status, options) {
+            Ok(0) | Err(sys::EINTR) => (),
+            Ok(_) if sys::wifexited(status) => return Some(sys::wexitstatus(status)),
+            Ok(_) if sys::wifsignaled(status) => return Some(128 + sys::wtermsig(status)),
+            Ok(_) => (),
+            Err(errno) => {
+                eprintln!("ion: timeout: waitpid error: {}", sys::strerror(errno));
+                return Some(FAILURE);
+            }
+        }
+
+        match deadline {
+            Some(deadline) if Instant::now() >= deadline => return None,
+            Some(_) => thread::sleep(Duration::from_millis(INTERVAL)),
+            None => (),
+        }
+    }
+}
+
+/// Waits until every process in the group has exited, returning `false` if the deadline passed
+/// before they did.
+fn group_exited(pgid: u32, deadline: Option<Instant>) -> bool {
+    while sys::killpg(pgid, 0).is_ok() {
+        if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(INTERVAL));
+    }
+    true
+}
+
+/// Sleeps for the sum of the given durations, or until interrupted if any of them is `infinity`.
+pub(crate) fn sleep(args: &[small::String]) -> i32 {
+    if args.is_empty() {
+        eprintln!("ion: sleep: missing duration");
+        return BAD_ARG;
+    }
+
+    // A total which is too long to be represented is as good as infinite.
+    let mut total = Some(Duration::from_secs(0));
+    for arg in args {
+        match arg.as_str() {
+            "inf" | "infinity" => total = None,
+            arg => match parse_duration(arg) {
+                Some(duration) => total = total.and_then(|total| total.checked_add(duration)),
+                None => {
+                    eprintln!("ion: sleep: invalid duration: {}", arg);
+                    return BAD_ARG;
+                }
+            },
+        }
+    }
+
+    if sleep_until(total.and_then(deadline_after)) {
+        SUCCESS
+    } else {
+        interrupted()
+    }
+}
+
+/// Executes a command within a process group of its own, which is sent a signal if the command
+/// runs for longer than the given duration. If a grace period was given with `-k`, the command
+/// is killed if it continues to run for that long after the signal was sent.
+///
+/// Returns the exit status of the command, or 124 if it was terminated for running too long.
+pub(crate) fn timeout(shell: &mut Shell, args: &[small::String]) -> i32 {
+    let mut signal = sys::SIGTERM;
+    let mut grace = None;
+    let mut duration = None;
+
+    let mut index = 0;
+    while let Some(arg) = args.get(index) {
+        match arg.as_str() {
+            "--" => {
+                index += 1;
+                break;
+            }
+            option @ "-s" | option @ "-k" => {
+                let value = match args.get(index + 1) {
+                    Some(value) => value,
+                    None => {
+                        eprintln!("ion: timeout: {} requires an argument", option);
+                        return BAD_ARG;
+                    }
+                };
+
+                if option == "-s" {
+                    match signals::from_name(value) {
+                        Some(number) => signal = number,
+                        None => {
+                            eprintln!("ion: timeout: {}: invalid signal", value);
+                            return BAD_ARG;
+                        }
+                    }
+                } else {
+                    match parse_duration(value) {
+                        Some(period) => grace = Some(period),
+                        None => {
+                            eprintln!("ion: timeout: invalid duration: {}", value);
+                            return BAD_ARG;
+                        }
+                    }
+                }
+                index += 2;
+            }
+            option if option.starts_with('-') => {
+                eprintln!("ion: timeout: invalid option: {}", option);
+                return BAD_ARG;
+            }
+            _ if duration.is_none() => {
+                match parse_duration(arg) {
+                    Some(limit) => duration = Some(limit),
+                    None => {
+                        eprintln!("ion: timeout: invalid duration: {}", arg);
+                        return BAD_ARG;
+                    }
+                }
+                index += 1;
+            }
+            _ => break,
+        }
+    }
+
+    let duration = match duration {
+        Some(duration) => duration,
+        None => {
+            eprintln!("ion: timeout: missing duration");
+            return BAD_ARG;
+        }
+    };
+
+    let command = &args[index..];
+    if command.is_empty() {
+        eprintln!("ion: timeout: missing command");
+        return BAD_ARG;
+    }
+
+    let pid = match pipe_exec::fork_command(shell, command) {
+        Ok(pid) => pid,
+        Err(why) => {
+            eprintln!("ion: timeout: failed to fork: {}", why);
+            return FAILURE;
+        }
+    };
+
+    // A limit which is too distant to be reached is waited for like no limit at all.
+    if let Some(status) = wait_until(pid, deadline_after(duration)) {
+        return status;
+    }
+
+    let _ = sys::killpg(pid, signal);
+    let _ = sys::killpg(pid, sys::SIGCONT);
+    let mut killed = signal == sys::SIGKILL;
+    let mut exited = false;
+    if let Some(grace) = grace {
+        // Every process in the group must exit within the grace period, including those which
+        // outlived the fork that executed them.
+        let deadline = deadline_after(grace);
+        exited = wait_until(pid, Some(deadline)).is_some();
+        if !exited || !group_exited(pid, deadline) {
+            let _ = sys::killpg(pid, sys::SIGKILL);
+            killed = true;
+        }
+    }
+    if !exited {
+        wait_until(pid, None);
+    }
+
+    // Like a command which was killed outright, a command which had to be killed reports so.
+    if killed {
+        128 + sys::SIGKILL
+    } else {
+        TIMED_OUT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("3"), Some(Duration::from_secs(3)));
+        assert_eq!(parse_duration("0.5"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1h"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_duration("1d"), Some(Duration::from_secs(86400)));
+        assert_eq!(parse_duration("-1"), None);
+        assert_eq!(parse_duration("5x"), None);
+        assert_eq!(parse_duration("inf"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+
+    #[test]
+    fn deadlines() {
+        assert!(deadline_after(Duration::from_secs(60)).is_some());
+        assert_eq!(deadline_after(Duration::from_secs(u64::max_value())), None);
+        let half = parse_duration("10000000000000000000").unwrap();
+        assert_eq!(half.checked_add(half), None);
+    }
+}
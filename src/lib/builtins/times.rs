@@ -0,0 +1,166 @@
+//! Contains the `times` builtin, and the formatting of the times reported by the `time` keyword.
+
+use crate::{shell::status::*, sys};
+use small;
+use std::{cell::Cell, time::Duration};
+
+/// The format of the times reported by `time`, when the `TIMEFORMAT` variable is not set.
+pub(crate) const DEFAULT_FORMAT: &str = "real\t%3lR\nuser\t%3lU\nsys\t%3lS";
+
+/// Formats a number of seconds with the given number of decimal places, which are truncated
+/// rather than rounded. The long format also gives the number of minutes, as in `1m2.345s`.
+fn format_seconds(duration: Duration, precision: usize, long: bool) -> String {
+    let seconds = duration.as_secs();
+    let fraction = if precision == 0 {
+        String::new()
+    } else {
+        let nanos = duration.subsec_nanos() / 10u32.pow(9 - precision as u32);
+        format!(".{:0width$}", nanos, width = precision)
+    };
+
+    if long {
+        format!("{}m{}{}s", seconds / 60, seconds % 60, fraction)
+    } else {
+        format!("{}{}", seconds, fraction)
+    }
+}
+
+/// Formats the real, user, and system times of a command according to a `TIMEFORMAT`.
+///
+/// Each `%R`, `%U`, and `%S` is replaced with the real, user, and system time in seconds, and
+/// `%P` with the percentage of the real time which was spent on the CPU. An optional precision
+/// from 0 to 3 may follow the `%`, followed by an optional `l` to select the long format of
+/// times, as in `%3lR`. A literal `%` is written as `%%`.
+pub(crate) fn format_times(
+    format: &str,
+    real: Duration,
+    user: Duration,
+    system: Duration,
+) -> String {
+    let mut output = String::with_capacity(format.len());
+    let mut chars = format.chars().peekable();
+    while let Some(character) = chars.next() {
+        if character != '%' {
+            output.push(character);
+            continue;
+        }
+
+        let mut sequence = String::from("%");
+        let precision = match chars.peek().and_then(|c| c.to_digit(10)) {
+            Some(digit) => {
+                sequence.extend(chars.next());
+                Some(digit.min(3) as usize)
+            }
+            None => None,
+        };
+        let long = chars.peek() == Some(&'l');
+        if long {
+            sequence.extend(chars.next());
+        }
+
+        match chars.next() {
+            Some('R') => output.push_str(&format_seconds(real, precision.unwrap_or(3), long)),
+            Some('U') => output.push_str(&format_seconds(user, precision.unwrap_or(3), long)),
+            Some('S') => output.push_str(&format_seconds(system, precision.unwrap_or(3), long)),
+            Some('P') => {
+                let nanos = |duration: Duration| {
+                    duration.as_secs() as f64 * 1e9 + f64::from(duration.subsec_nanos())
+                };
+                let percentage = if real == Duration::from_secs(0) {
+                    0.0
+                } else {
+                    (nanos(user) + nanos(system)) / nanos(real) * 100.0
+                };
+                output.push_str(&format!("{:.*}", precision.unwrap_or(2), percentage));
+            }
+            Some('%') if sequence.len() == 1 => output.push('%'),
+            // Sequences which are not understood are written as they were given.
+            Some(character) => {
+                output.push_str(&sequence);
+                output.push(character);
+            }
+            None => output.push_str(&sequence),
+        }
+    }
+    output
+}
+
+thread_local! {
+    /// The CPU times of the children which the shell has waited upon itself. Unlike the times
+    /// given for `RUSAGE_CHILDREN`, these exclude the background jobs collected by the reaper.
+    static WAITED: Cell<(Duration, Duration)> = Cell::new(Default::default());
+}
+
+/// Records the CPU times of a child which has exited, as returned by `sys::wait4`.
+pub(crate) fn record_child(times: (Duration, Duration)) {
+    WAITED.with(|waited| {
+        let (user, system) = waited.get();
+        waited.set((user + times.0, system + times.1));
+    });
+}
+
+/// The user and system CPU times consumed by the shell, and by those of its children which it
+/// has waited upon itself. Times which cannot be obtained are counted as zero.
+pub(crate) fn cpu_times() -> (Duration, Duration) {
+    let (mut user, mut system) = WAITED.with(Cell::get);
+    if let Ok(times) = sys::getrusage(sys::RUSAGE_SELF) {
+        user += times.0;
+        system += times.1;
+    }
+    (user, system)
+}
+
+/// Displays the user and system times consumed by the shell, followed by those consumed by the
+/// commands that it has executed.
+pub(crate) fn times(args: &[small::String]) -> i32 {
+    if !args.is_empty() {
+        eprintln!("ion: times: too many arguments");
+        return BAD_ARG;
+    }
+
+    for &who in &[sys::RUSAGE_SELF, sys::RUSAGE_CHILDREN] {
+        match sys::getrusage(who) {
+            Ok((user, system)) => println!(
+                "{} {}",
+                format_seconds(user, 3, true),
+                format_seconds(system, 3, true)
+            ),
+            Err(why) => {
+                eprintln!("ion: times: {}", why);
+                return FAILURE;
+            }
+        }
+    }
+    SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seconds() {
+        let duration = Duration::new(62, 345_678_901);
+        assert_eq!(format_seconds(duration, 3, false), "62.345");
+        assert_eq!(format_seconds(duration, 3, true), "1m2.345s");
+        assert_eq!(format_seconds(duration, 1, false), "62.3");
+        assert_eq!(format_seconds(duration, 0, true), "1m2s");
+        assert_eq!(format_seconds(Duration::from_millis(5), 3, false), "0.005");
+    }
+
+    #[test]
+    fn formats() {
+        let real = Duration::from_millis(2000);
+        let user = Duration::from_millis(1250);
+        let system = Duration::from_millis(250);
+        assert_eq!(
+            format_times(DEFAULT_FORMAT, real, user, system),
+            "real\t0m2.000s\nuser\t0m1.250s\nsys\t0m0.250s"
+        );
+        assert_eq!(format_times("%R %1U %0S", real, user, system), "2.000 1.2 0");
+        assert_eq!(format_times("%P%% %1P", real, user, system), "75.00% 75.0");
+        assert_eq!(format_times("%9R", real, user, system), "2.000");
+        assert_eq!(format_times("%x %", real, user, system), "%x %");
+        assert_eq!(format_times("", real, user, system), "");
+    }
+}
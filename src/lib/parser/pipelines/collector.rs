@@ -462,6 +462,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn bypassing_functions() {
+        let input = "command ls -l | builtin echo a | builtin ls";
+        if let Statement::Pipeline(pipeline) = parse(input) {
+            let items = pipeline.items;
+            assert_eq!("ls", items[0].job.command.as_str());
+            assert_eq!("-l", items[0].job.args[1].as_str());
+            assert!(items[0].job.direct && items[0].job.builtin.is_none());
+            assert_eq!("echo", items[1].job.args[0].as_str());
+            assert!(items[1].job.direct && items[1].job.builtin.is_some());
+            assert_eq!("builtin", items[2].job.args[0].as_str());
+            assert!(!items[2].job.direct);
+        } else {
+            assert!(false);
+        }
+
+        if let Statement::Pipeline(pipeline) = parse("command -v ls") {
+            assert_eq!("command", pipeline.items[0].job.args[0].as_str());
+            assert!(!pipeline.items[0].job.direct);
+        } else {
+            assert!(false);
+        }
+    }
+
     #[test]
     fn braces() {
         if let Statement::Pipeline(pipeline) = parse("echo {a b} {a {b c}}") {
@@ -133,11 +133,22 @@ pub(crate) fn parse(code: &str) -> Statement {
             }
 
             match variables {
-                Some(variables) => Statement::For {
-                    variables,
-                    values: ArgumentSplitter::new(cmd).map(small::String::from).collect(),
-                    statements: Vec::new(),
-                },
+                Some(mut variables) => {
+                    // A leading `--jobs N` executes up to N iterations concurrently.
+                    let jobs = variables.get(0).map_or(false, |flag| flag.as_str() == "--jobs");
+                    let parallel = if jobs {
+                        if variables.len() < 3 {
+                            eprintln!("ion: syntax error: for loop lacks a number of jobs");
+                            return Statement::Error(FAILURE);
+                        }
+                        variables.remove(0);
+                        Some(variables.remove(0))
+                    } else {
+                        None
+                    };
+                    let values = ArgumentSplitter::new(cmd).map(small::String::from).collect();
+                    Statement::For { variables, values, parallel, statements: Vec::new() }
+                }
                 None => {
                     eprintln!("ion: syntax error: for loop lacks the `in` keyword");
                     Statement::Error(FAILURE)
@@ -233,6 +244,7 @@ mod tests {
             Statement::For {
                 variables:  vec!["x", "y", "z"].into_iter().map(Into::into).collect(),
                 values:     vec!["1..=10"].into_iter().map(Into::into).collect(),
+                parallel:   None,
                 statements: Vec::new(),
             }
         );
@@ -242,9 +254,32 @@ mod tests {
             Statement::For {
                 variables:  vec!["x"].into_iter().map(Into::into).collect(),
                 values:     vec!["{1..=10}", "{1..=10}"].into_iter().map(Into::into).collect(),
+                parallel:   None,
+                statements: Vec::new(),
+            }
+        );
+
+        assert_eq!(
+            parse("for --jobs $jobs file in @files *.txt"),
+            Statement::For {
+                variables:  vec!["file"].into_iter().map(Into::into).collect(),
+                values:     vec!["@files", "*.txt"].into_iter().map(Into::into).collect(),
+                parallel:   Some("$jobs".into()),
+                statements: Vec::new(),
+            }
+        );
+
+        assert_eq!(
+            parse("for word in parallel 8"),
+            Statement::For {
+                variables:  vec!["word"].into_iter().map(Into::into).collect(),
+                values:     vec!["parallel", "8"].into_iter().map(Into::into).collect(),
+                parallel:   None,
                 statements: Vec::new(),
             }
         );
+
+        assert_eq!(parse("for --jobs 8 in 1 2"), Statement::Error(FAILURE));
     }
 
     #[test]
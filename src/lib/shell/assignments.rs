@@ -65,6 +65,9 @@ impl VariableStore for Shell {
                 for action in actions {
                     let err = action.map_err(|e| e.to_string()).and_then(|act| {
                         let Action(key, operator, expression) = act;
+                        if key.name == "PATH" {
+                            self.command_hash.clear();
+                        }
                         value_check(self, &expression, &key.kind)
                             .map_err(|e| format!("{}: {}", key.name, e))
                             // TODO: handle operators here in the same way as local
@@ -151,6 +154,12 @@ impl VariableStore for Shell {
                 }
             }
 
+            // Assigning the PATH forgets where commands were found, as `PATH=$PATH` does in
+            // other shells to pick up commands which were installed since.
+            if key.name == "PATH" {
+                self.command_hash.clear();
+            }
+
             match (&rhs, &key.kind) {
                 (Value::HashMap(_), Primitive::Indexed(..)) => {
                     Err("cannot insert hmap into index".to_string())?
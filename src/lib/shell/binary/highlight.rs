@@ -0,0 +1,470 @@
+//! Live syntax highlighting of the command line while it is being edited.
+//!
+//! Statements are located with the `StatementSplitter`, validated with the pipeline `Collector`
+//! and the `Terminator`, and then each statement is scanned word by word in order to assign a
+//! style to every byte of the line. Colors are configured with the same names that are accepted
+//! by the `${c::...}` namespace, through the `HIGHLIGHT_*` variables.
+
+use super::super::{colors::Colors, Shell};
+use crate::{
+    parser::{
+        pipelines::Collector,
+        statement::{StatementError, StatementVariant},
+        StatementSplitter, Terminator,
+    },
+    sys, types,
+};
+use hashbrown::HashMap;
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Words which are interpreted by the shell's grammar rather than executed as commands.
+const KEYWORDS: &[&str] = &[
+    "!", "and", "break", "case", "continue", "else", "end", "export", "fn", "for", "if", "in",
+    "let", "match", "not", "or", "time", "while",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Style {
+    Plain,
+    Command,
+    Unknown,
+    Keyword,
+    Str,
+    Variable,
+    Redirection,
+    Error,
+}
+
+/// The escape sequences that will be emitted for each style.
+struct Palette {
+    command:     String,
+    unknown:     String,
+    keyword:     String,
+    string:      String,
+    variable:    String,
+    redirection: String,
+    error:       String,
+}
+
+impl Palette {
+    fn new(shell: &Shell) -> Palette {
+        let color = |variable: &str, default: &str| {
+            let name = shell.variables.get::<types::Str>(variable);
+            Colors::collect(name.as_ref().map_or(default, |name| name.as_str()))
+                .into_string()
+                .unwrap_or_default()
+        };
+
+        Palette {
+            command:     color("HIGHLIGHT_COMMAND", "green"),
+            unknown:     color("HIGHLIGHT_UNKNOWN", "red"),
+            keyword:     color("HIGHLIGHT_KEYWORD", "magenta"),
+            string:      color("HIGHLIGHT_STRING", "yellow"),
+            variable:    color("HIGHLIGHT_VARIABLE", "cyan"),
+            redirection: color("HIGHLIGHT_REDIRECTION", "blue"),
+            error:       color("HIGHLIGHT_ERROR", "red,underlined"),
+        }
+    }
+
+    fn get(&self, style: Style) -> &str {
+        match style {
+            Style::Plain => "",
+            Style::Command => &self.command,
+            Style::Unknown => &self.unknown,
+            Style::Keyword => &self.keyword,
+            Style::Str => &self.string,
+            Style::Variable => &self.variable,
+            Style::Redirection => &self.redirection,
+            Style::Error => &self.error,
+        }
+    }
+}
+
+/// A snapshot of the shell state that is needed to highlight a line, which may be moved into
+/// the color closure that Liner calls after every key press.
+pub(crate) struct Highlighter {
+    commands: HashSet<String>,
+    paths:    Vec<PathBuf>,
+    cache:    RefCell<HashMap<String, bool>>,
+    palette:  Palette,
+}
+
+impl Highlighter {
+    /// Returns `None` if highlighting was disabled with `HIGHLIGHT_ENABLED`.
+    pub(crate) fn new(shell: &Shell) -> Option<Highlighter> {
+        if "0" == shell.get_str_or_empty("HIGHLIGHT_ENABLED") {
+            return None;
+        }
+
+        let commands = shell
+            .command_names()
+            .chain(shell.abbreviations.keys().map(|name| name.to_string()))
+            .collect();
+
+        let paths = env::var("PATH")
+            .unwrap_or_else(|_| "/bin".to_owned())
+            .split(sys::PATH_SEPARATOR)
+            .map(PathBuf::from)
+            .collect();
+
+        Some(Highlighter {
+            commands,
+            paths,
+            cache: RefCell::new(HashMap::new()),
+            palette: Palette::new(shell),
+        })
+    }
+
+    /// Wraps each styled region of the line in the escape sequences of its style.
+    pub(crate) fn highlight(&self, line: &str) -> String {
+        let styles = self.styles(line);
+        let mut output = String::with_capacity(line.len() * 2);
+        let mut current = Style::Plain;
+        for (index, character) in line.char_indices() {
+            let style = styles[index];
+            if style != current {
+                if current != Style::Plain {
+                    output.push_str("\x1b[0m");
+                }
+                output.push_str(self.palette.get(style));
+                current = style;
+            }
+            output.push(character);
+        }
+
+        if current != Style::Plain {
+            output.push_str("\x1b[0m");
+        }
+        output
+    }
+
+    fn styles(&self, line: &str) -> Vec<Style> {
+        let mut styles = vec![Style::Plain; line.len()];
+        let mut cursor = 0;
+        for statement in StatementSplitter::new(line) {
+            match statement {
+                Ok(statement) => {
+                    let text = match statement {
+                        StatementVariant::And(text)
+                        | StatementVariant::Or(text)
+                        | StatementVariant::Default(text) => text,
+                    };
+
+                    // Locate the statement within the line, as the splitter may return slices
+                    // that were trimmed or, for `else`, not borrowed from the line at all.
+                    if let Some(position) = line[cursor..].find(text) {
+                        let offset = cursor + position;
+                        cursor = offset + text.len();
+                        self.statement(text, &mut styles[offset..cursor]);
+                        if Collector::run(text).is_err() {
+                            paint(&mut styles[offset..cursor], Style::Error, |s| s == Style::Plain);
+                        }
+                    }
+                }
+                Err(StatementError::InvalidCharacter(_, position)) => {
+                    if let Some(style) = styles.get_mut(position.saturating_sub(1)) {
+                        *style = Style::Error;
+                    }
+                }
+                // Unterminated subshells, braces and methods continue to the end of the line.
+                Err(_) => {
+                    let end = styles.len();
+                    paint(&mut styles[cursor..end], Style::Error, |_| true);
+                    break;
+                }
+            }
+        }
+
+        // Quotes which are still open when the line ends are marked as errors, so that the user
+        // knows why the command will not be executed when Enter is pressed.
+        if let Some(Err(())) = Terminator::new(line.bytes().chain(Some(b'\n'))).terminate() {
+            if let Some(start) = unterminated_quote(line) {
+                let end = styles.len();
+                paint(&mut styles[start..end], Style::Error, |_| true);
+            }
+        }
+
+        styles
+    }
+
+    /// Assigns styles to the words of a single statement.
+    fn statement(&self, text: &str, styles: &mut [Style]) {
+        let bytes = text.as_bytes();
+        let mut command_next = true;
+        let mut first_word = true;
+        let mut index = 0;
+
+        while index < bytes.len() {
+            match bytes[index] {
+                b' ' | b'\t' => index += 1,
+                b'|' => {
+                    styles[index] = Style::Redirection;
+                    command_next = true;
+                    index += 1;
+                }
+                b'^' if bytes.get(index + 1) == Some(&b'|') => {
+                    paint(&mut styles[index..index + 2], Style::Redirection, |_| true);
+                    command_next = true;
+                    index += 2;
+                }
+                b'&' if bytes.get(index + 1) == Some(&b'|') => {
+                    paint(&mut styles[index..index + 2], Style::Redirection, |_| true);
+                    command_next = true;
+                    index += 2;
+                }
+                b'^' | b'&' if bytes.get(index + 1) == Some(&b'>') => {
+                    let end = redirection_end(bytes, index + 1, b'>');
+                    paint(&mut styles[index..end], Style::Redirection, |_| true);
+                    index = end;
+                }
+                b'&' => {
+                    let end =
+                        if bytes.get(index + 1) == Some(&b'!') { index + 2 } else { index + 1 };
+                    paint(&mut styles[index..end], Style::Redirection, |_| true);
+                    index = end;
+                }
+                b'>' | b'<' => {
+                    let end = redirection_end(bytes, index, bytes[index]);
+                    paint(&mut styles[index..end], Style::Redirection, |_| true);
+                    index = end;
+                }
+                _ => {
+                    let end = self.word(bytes, index, styles);
+                    if command_next {
+                        let word = &text[index..end];
+                        if first_word && KEYWORDS.contains(&word) {
+                            paint(&mut styles[index..end], Style::Keyword, |_| true);
+                            // Conditional keywords are followed by a command.
+                            command_next = match word {
+                                "if" | "while" | "and" | "or" | "not" | "!" | "time" | "else" => {
+                                    true
+                                }
+                                _ => false,
+                            };
+                            first_word = word == "else" || command_next;
+                            index = end;
+                            continue;
+                        }
+
+                        if !word.contains(|c| c == '$' || c == '@' || c == '\'' || c == '"') {
+                            let style =
+                                if self.exists(word) { Style::Command } else { Style::Unknown };
+                            paint(&mut styles[index..end], style, |_| true);
+                        }
+                        command_next = false;
+                    }
+                    first_word = false;
+                    index = end;
+                }
+            }
+        }
+    }
+
+    /// Styles the strings and variables within a word, and returns the index where it ends.
+    fn word(&self, bytes: &[u8], mut index: usize, styles: &mut [Style]) -> usize {
+        let mut level = 0u8;
+        while index < bytes.len() {
+            match bytes[index] {
+                b' ' | b'\t' | b'|' | b'&' | b'>' | b'<' if level == 0 => break,
+                b'^' if level == 0
+                    && (bytes.get(index + 1) == Some(&b'>')
+                        || bytes.get(index + 1) == Some(&b'|')) =>
+                {
+                    break
+                }
+                b'\\' => index += 2,
+                b'\'' => {
+                    let end = bytes[index + 1..]
+                        .iter()
+                        .position(|&b| b == b'\'')
+                        .map_or(bytes.len(), |pos| index + pos + 2);
+                    paint(&mut styles[index..end], Style::Str, |_| true);
+                    index = end;
+                }
+                b'"' => {
+                    let start = index;
+                    index += 1;
+                    while index < bytes.len() && bytes[index] != b'"' {
+                        match bytes[index] {
+                            b'\\' => index += 2,
+                            b'$' | b'@' => index = variable(bytes, index, styles),
+                            _ => {
+                                styles[index] = Style::Str;
+                                index += 1;
+                            }
+                        }
+                    }
+                    index = bytes.len().min(index + 1);
+                    styles[start] = Style::Str;
+                    if let Some(style) = styles.get_mut(index - 1) {
+                        *style = Style::Str;
+                    }
+                }
+                b'$' | b'@' => index = variable(bytes, index, styles),
+                b'(' | b'[' | b'{' => {
+                    level += 1;
+                    index += 1;
+                }
+                b')' | b']' | b'}' => {
+                    level = level.saturating_sub(1);
+                    index += 1;
+                }
+                _ => index += 1,
+            }
+        }
+        index.min(bytes.len())
+    }
+
+    /// Determines if the word names a builtin, function, alias, directory or executable.
+    fn exists(&self, word: &str) -> bool {
+        if self.commands.contains(word) {
+            return true;
+        }
+
+        if let Some(&exists) = self.cache.borrow().get(word) {
+            return exists;
+        }
+
+        let exists = if word.contains('/') || word.starts_with('.') {
+            Path::new(word).exists()
+        } else {
+            self.paths.iter().any(|path| path.join(word).is_file())
+        };
+
+        self.cache.borrow_mut().insert(word.to_owned(), exists);
+        exists
+    }
+}
+
+/// Paints each style of the slice which satisfies the predicate with the given style.
+fn paint<F: Fn(Style) -> bool>(styles: &mut [Style], style: Style, predicate: F) {
+    for current in styles.iter_mut().filter(|s| predicate(**s)) {
+        *current = style;
+    }
+}
+
+/// Returns the index after a redirection operator, such as `>`, `>>`, `<<<`.
+fn redirection_end(bytes: &[u8], mut index: usize, operator: u8) -> usize {
+    while bytes.get(index) == Some(&operator) {
+        index += 1;
+    }
+    index
+}
+
+/// Styles a variable, array, process or method expansion beginning at `index`, returning the
+/// index after the expansion.
+fn variable(bytes: &[u8], index: usize, styles: &mut [Style]) -> usize {
+    let mut end = index + 1;
+    match bytes.get(end) {
+        Some(&open) if open == b'(' || open == b'{' => {
+            let close = if open == b'(' { b')' } else { b'}' };
+            let mut level = 0;
+            while end < bytes.len() {
+                if bytes[end] == open {
+                    level += 1;
+                } else if bytes[end] == close {
+                    level -= 1;
+                    if level == 0 {
+                        end += 1;
+                        break;
+                    }
+                }
+                end += 1;
+            }
+        }
+        _ => {
+            while end < bytes.len()
+                && (bytes[end].is_ascii_alphanumeric() || bytes[end] == b'_' || bytes[end] == b':')
+            {
+                end += 1;
+            }
+            // A method, such as `$join(array)`, includes its arguments.
+            if bytes.get(end) == Some(&b'(') {
+                let mut level = 0;
+                while end < bytes.len() {
+                    match bytes[end] {
+                        b'(' => level += 1,
+                        b')' => {
+                            level -= 1;
+                            if level == 0 {
+                                end += 1;
+                                break;
+                            }
+                        }
+                        _ => (),
+                    }
+                    end += 1;
+                }
+            }
+        }
+    }
+
+    let end = end.min(bytes.len());
+    paint(&mut styles[index..end], Style::Variable, |_| true);
+    end
+}
+
+/// Finds the position of a quote that is never closed.
+fn unterminated_quote(line: &str) -> Option<usize> {
+    let mut open = None;
+    let mut bytes = line.bytes().enumerate();
+    while let Some((index, byte)) = bytes.next() {
+        match (byte, open) {
+            (b'\\', Some((_, b'"'))) | (b'\\', None) => {
+                bytes.next();
+            }
+            (b'\'', None) | (b'"', None) => open = Some((index, byte)),
+            (_, Some((_, quote))) if quote == byte => open = None,
+            _ => (),
+        }
+    }
+    open.map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::ShellBuilder;
+
+    fn styles_of(line: &str) -> Vec<Style> {
+        let shell = ShellBuilder::new().as_library();
+        Highlighter::new(&shell).unwrap().styles(line)
+    }
+
+    #[test]
+    fn builtins_and_keywords() {
+        let styles = styles_of("if echo foo");
+        assert_eq!(&styles[0..2], &[Style::Keyword; 2]);
+        assert_eq!(&styles[3..7], &[Style::Command; 4]);
+        assert_eq!(&styles[8..], &[Style::Plain; 3]);
+    }
+
+    #[test]
+    fn unknown_commands() {
+        let styles = styles_of("ion_no_such_command_here arg");
+        assert!(styles[..24].iter().all(|&s| s == Style::Unknown));
+        assert!(styles[25..].iter().all(|&s| s == Style::Plain));
+    }
+
+    #[test]
+    fn strings_variables_and_redirections() {
+        let styles = styles_of("echo 'a' $b > c");
+        assert_eq!(&styles[5..8], &[Style::Str; 3]);
+        assert_eq!(&styles[9..11], &[Style::Variable; 2]);
+        assert_eq!(styles[12], Style::Redirection);
+        assert_eq!(styles[14], Style::Plain);
+    }
+
+    #[test]
+    fn unterminated_quotes() {
+        let styles = styles_of("echo \"foo");
+        assert!(styles[5..].iter().all(|&s| s == Style::Error));
+        assert_eq!(unterminated_quote("echo 'a' \"b"), Some(9));
+        assert_eq!(unterminated_quote("echo 'a\"' b"), None);
+    }
+}
@@ -1,5 +1,6 @@
 //! Contains the binary logic of Ion.
 mod designators;
+mod highlight;
 mod prompt;
 mod readln;
 mod terminate;
@@ -88,6 +89,10 @@ impl Binary for Shell {
             context
         });
 
+        // Changes in the state of background jobs are reported before the next prompt, unless
+        // the init file enables `set -b`.
+        self.notifications.set_interactive(true);
+        self.notifications.set_immediate(false);
         self.evaluate_init_file();
 
         loop {
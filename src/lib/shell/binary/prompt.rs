@@ -1,9 +1,24 @@
 use crate::{
     parser::shell_expand::expand_string,
-    shell::{flags::UNTERMINATED, Capture, Function, Shell},
-    sys,
+    shell::{
+        flags::{ERR_EXIT, UNTERMINATED},
+        flow_control::Statement,
+        Function, Shell,
+    },
+    sys, types,
+};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    os::unix::io::{AsRawFd, FromRawFd, RawFd},
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
 };
-use std::{io::Read, process};
 
 pub(crate) fn prompt(shell: &mut Shell) -> String {
     let blocks =
@@ -14,34 +29,317 @@ pub(crate) fn prompt(shell: &mut Shell) -> String {
             expand_string(&shell.get_str_or_empty("PROMPT"), shell, false).join(" ")
         })
     } else {
-        "    ".repeat(blocks)
+        continuation(shell, blocks)
     }
 }
 
 pub(crate) fn prompt_fn(shell: &mut Shell) -> Option<String> {
-    let function = shell.variables.get::<Function>("PROMPT")?;
-    let function = &function as *const Function;
-
-    let mut output = None;
-
-    match shell.fork(Capture::StdoutThenIgnoreStderr, |child| {
-        let _ = unsafe { function.read() }.execute(child, &["ion"]);
-    }) {
-        Ok(result) => {
-            let mut string = String::with_capacity(1024);
-            match result.stdout.unwrap().read_to_string(&mut string) {
-                Ok(_) => output = Some(string),
-                Err(why) => {
-                    eprintln!("ion: error reading stdout of child: {}", why);
-                }
+    function_output(shell, "PROMPT", &["ion"])
+}
+
+/// The prompt which is displayed right-aligned on the same line as the primary prompt, if
+/// either an `RPROMPT` function or variable was defined.
+pub(crate) fn rprompt(shell: &mut Shell) -> Option<String> {
+    if is_continuation(shell) {
+        return None;
+    }
+
+    string_or_function(shell, "RPROMPT", &["ion"])
+        .map(|rprompt| rprompt.trim_end_matches('\n').to_owned())
+        .filter(|rprompt| !rprompt.is_empty())
+}
+
+/// The prompt which replaces the primary prompt in the scrollback once a command is accepted, if
+/// either a `TRANSIENT_PROMPT` function or variable was defined.
+pub(crate) fn transient_prompt(shell: &mut Shell) -> Option<String> {
+    if is_continuation(shell) {
+        return None;
+    }
+
+    string_or_function(shell, "TRANSIENT_PROMPT", &["ion"])
+}
+
+/// Generates the prompt for a line which continues an unterminated statement or an open block.
+///
+/// If `PROMPT_CONTINUATION` is a function, it is given the kinds of each open block as a single
+/// argument, from the outermost to the innermost, whereas a string is repeated once per block.
+fn continuation(shell: &mut Shell, blocks: usize) -> String {
+    let mut kinds: Vec<&str> = shell.flow_control.block.iter().map(block_kind).collect();
+    if shell.flags & UNTERMINATED != 0 {
+        kinds.push("unterminated");
+    }
+    let kinds = kinds.join(" ");
+
+    if let Some(output) = function_output(shell, "PROMPT_CONTINUATION", &["ion", &kinds[..]]) {
+        return output;
+    }
+
+    match shell.variables.get::<types::Str>("PROMPT_CONTINUATION") {
+        Some(continuation) => expand_string(&continuation, shell, false).join(" ").repeat(blocks),
+        None => "    ".repeat(blocks),
+    }
+}
+
+fn block_kind(statement: &Statement) -> &'static str {
+    match *statement {
+        Statement::If { .. } => "if",
+        Statement::For { .. } => "for",
+        Statement::While { .. } => "while",
+        Statement::Match { .. } => "match",
+        Statement::Case(_) => "case",
+        Statement::Function { .. } => "fn",
+        _ => "block",
+    }
+}
+
+fn is_continuation(shell: &Shell) -> bool {
+    !shell.flow_control.block.is_empty() || shell.flags & UNTERMINATED != 0
+}
+
+/// Prefers the output of the function with the given name, falling back to expanding the
+/// variable of the same name.
+fn string_or_function(shell: &mut Shell, name: &str, args: &[&str]) -> Option<String> {
+    function_output(shell, name, args).or_else(|| {
+        let value = shell.variables.get::<types::Str>(name)?;
+        Some(expand_string(&value, shell, false).join(" "))
+    })
+}
+
+/// Executes the function with the given name, if it exists, and returns what it wrote to the
+/// standard output.
+///
+/// The function is evaluated within the shell rather than a fork, so that any variables which it
+/// sets are retained for the next prompt, but the exit status of the previous command is kept.
+/// What the function writes to the standard error is discarded, as it was when the function was
+/// evaluated within a fork, and `exit` only ends the function.
+fn function_output(shell: &mut Shell, name: &str, args: &[&str]) -> Option<String> {
+    let function = shell.variables.get::<Function>(name)?;
+
+    let (reader, writer) = match sys::pipe2(sys::O_CLOEXEC) {
+        Ok((reader, writer)) => (sys::raise_fd(reader), writer),
+        Err(why) => {
+            eprintln!("ion: unable to create pipe for {}: {}", name, why);
+            return None;
+        }
+    };
+
+    let (stdout, stderr) = match (sys::dup(sys::STDOUT_FILENO), sys::dup(sys::STDERR_FILENO)) {
+        (Ok(stdout), Ok(stderr)) => (sys::raise_fd(stdout), sys::raise_fd(stderr)),
+        (stdout, stderr) => {
+            for fd in stdout.iter().chain(stderr.iter()) {
+                let _ = sys::close(*fd);
             }
+            eprintln!("ion: unable to duplicate the standard streams for {}", name);
+            let _ = sys::close(reader);
+            let _ = sys::close(writer);
+            return None;
         }
-        Err(why) => {
-            eprintln!("ion: fork error: {}", why);
+    };
+
+    // The output is read concurrently, so that a function which writes more than the pipe can
+    // buffer will not block.
+    let returned = Arc::new(AtomicBool::new(false));
+    let reader = {
+        let returned = returned.clone();
+        thread::spawn(move || read_output(reader, &returned))
+    };
+
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+    let _ = sys::dup2(writer, sys::STDOUT_FILENO);
+    let _ = sys::close(writer);
+    if let Ok(null) = OpenOptions::new().write(true).open(sys::NULL_PATH) {
+        let _ = sys::dup2(null.as_raw_fd(), sys::STDERR_FILENO);
+    }
+
+    let result = {
+        let mut evaluation = Evaluation::new(shell, stdout, stderr, &returned);
+        function.execute(evaluation.shell, args)
+    };
+
+    if let Err(why) = result {
+        eprintln!("ion: {} function: {}", name, why);
+    }
+
+    reader.join().ok()
+}
+
+/// The state of the shell which is altered while a prompt function is evaluated, and which is
+/// restored once the evaluation is dropped, however the function ends.
+struct Evaluation<'a> {
+    shell:           &'a mut Shell,
+    flags:           u8,
+    previous_status: i32,
+    stdout:          RawFd,
+    stderr:          RawFd,
+    returned:        &'a AtomicBool,
+}
+
+impl<'a> Evaluation<'a> {
+    fn new(shell: &'a mut Shell, stdout: RawFd, stderr: RawFd, returned: &'a AtomicBool) -> Self {
+        let flags = shell.flags;
+        let previous_status = shell.previous_status;
+        shell.flags &= !ERR_EXIT;
+        shell.generating_prompt = true;
+        Evaluation { shell, flags, previous_status, stdout, stderr, returned }
+    }
+}
+
+impl<'a> Drop for Evaluation<'a> {
+    fn drop(&mut self) {
+        self.shell.generating_prompt = false;
+        self.shell.flags = self.flags;
+        self.shell.break_flow = false;
+        self.shell.previous_status = self.previous_status;
+        self.shell.set("?", self.previous_status.to_string());
+
+        // Restoring the original stdout closes the shell's writer, which completes the reader
+        // unless a command which the function started is still running.
+        let _ = io::stdout().flush();
+        let _ = sys::dup2(self.stdout, sys::STDOUT_FILENO);
+        let _ = sys::close(self.stdout);
+        let _ = sys::dup2(self.stderr, sys::STDERR_FILENO);
+        let _ = sys::close(self.stderr);
+        self.returned.store(true, Ordering::SeqCst);
+
+        // Ensure that the shell retains ownership of the terminal.
+        let _ = sys::tcsetpgrp(sys::STDIN_FILENO, process::id());
+    }
+}
+
+/// Reads the output of a prompt function until the pipe is closed. Once the function has
+/// returned, reading also stops as soon as no more output is available, as a process which the
+/// function left running may hold the pipe open indefinitely.
+fn read_output(fd: RawFd, returned: &AtomicBool) -> String {
+    let mut reader = unsafe { File::from_raw_fd(fd) };
+    let mut output = Vec::with_capacity(1024);
+    let mut buffer = [0; 4096];
+    loop {
+        let finished = returned.load(Ordering::SeqCst);
+        match sys::poll_input(fd, Duration::from_millis(10)) {
+            Ok(false) if finished => break,
+            Ok(false) => continue,
+            Err(ref why) if why.kind() == io::ErrorKind::Interrupted => continue,
+            // Platforms which are unable to poll the pipe wait for it to be closed instead.
+            Ok(true) | Err(_) => (),
+        }
+
+        match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => output.extend_from_slice(&buffer[..read]),
+            Err(ref why) if why.kind() == io::ErrorKind::Interrupted => (),
+            Err(why) => {
+                eprintln!("ion: error reading prompt output: {}", why);
+                break;
+            }
         }
     }
+    String::from_utf8_lossy(&output).into_owned()
+}
 
-    // Ensure that the parent retains ownership of the terminal before exiting.
-    let _ = sys::tcsetpgrp(sys::STDIN_FILENO, process::id());
-    output
+/// The number of columns that the text occupies on the terminal, ignoring escape sequences.
+pub(crate) fn width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(character) = chars.next() {
+        match character {
+            '\x1b' => match chars.next() {
+                // Control sequences end with a byte in the range of `@` to `~`.
+                Some('[') => {
+                    for character in &mut chars {
+                        if character >= '@' && character <= '~' {
+                            break;
+                        }
+                    }
+                }
+                // Operating system commands end with either BEL or ST.
+                Some(']') => {
+                    while let Some(character) = chars.next() {
+                        if character == '\x07' {
+                            break;
+                        } else if character == '\x1b' {
+                            chars.next();
+                            break;
+                        }
+                    }
+                }
+                _ => (),
+            },
+            _ if character.is_control() => (),
+            _ => width += 1,
+        }
+    }
+    width
+}
+
+/// Appends the right prompt to the output, such that the cursor returns to where it was, as
+/// long as the right prompt would not overlap with the line that is being edited.
+pub(crate) fn right_align(output: &mut String, used: usize, rprompt: &str, columns: usize) {
+    let rprompt_width = width(rprompt);
+    if used + rprompt_width < columns {
+        let column = columns - rprompt_width + 1;
+        output.push_str(&format!("\x1b[s\x1b[{}G{}\x1b[0m\x1b[u", column, rprompt));
+    }
+}
+
+/// The number of rows on the terminal that are occupied by the text.
+pub(crate) fn rows(text: &str, columns: usize) -> usize {
+    text.split('\n').map(|line| width(line).saturating_sub(1) / columns.max(1) + 1).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::ShellBuilder;
+    use std::time::Instant;
+
+    #[test]
+    fn background_jobs_within_prompt() {
+        let mut shell = ShellBuilder::new().as_library();
+        shell.execute_command(&"fn PROMPT\n sleep 5 &\n echo 'ion# '\n end\n").unwrap();
+
+        // The job which the function started holds neither the output nor the prompt.
+        let start = Instant::now();
+        assert_eq!(prompt_fn(&mut shell), Some("ion# \n".into()));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn exit_within_prompt() {
+        let mut shell = ShellBuilder::new().as_library();
+        shell.execute_command(&"fn PROMPT\n echo 'ion# '\n exit 3\n echo no\n end\n").unwrap();
+        shell.previous_status = 1;
+
+        assert_eq!(prompt_fn(&mut shell), Some("ion# \n".into()));
+        assert_eq!(shell.previous_status, 1);
+        assert!(!shell.generating_prompt);
+        assert!(!shell.break_flow);
+    }
+
+    #[test]
+    fn widths() {
+        assert_eq!(width("ion# "), 5);
+        assert_eq!(width("\x1b[0;32mion\x1b[0m# "), 5);
+        assert_eq!(width("\x1b]0;title\x07$ "), 2);
+        assert_eq!(width("\x1b]0;title\x1b\\$ "), 2);
+    }
+
+    #[test]
+    fn right_alignment() {
+        let mut output = String::from("ls");
+        right_align(&mut output, 7, "12:00", 20);
+        assert_eq!(output, "ls\x1b[s\x1b[16G12:00\x1b[0m\x1b[u");
+
+        let mut output = String::from("ls");
+        right_align(&mut output, 15, "12:00", 20);
+        assert_eq!(output, "ls");
+    }
+
+    #[test]
+    fn row_count() {
+        assert_eq!(rows("ion# ls", 80), 1);
+        assert_eq!(rows("~\nion# ls", 80), 2);
+        assert_eq!(rows(&"x".repeat(81), 80), 2);
+        assert_eq!(rows(&"x".repeat(80), 80), 1);
+    }
 }
@@ -1,7 +1,19 @@
-use super::super::{completer::*, flags, Binary, DirectoryStack, Shell, Variables};
+use super::{
+    super::{completer::*, flags, variables::git, Binary, DirectoryStack, Shell, Variables},
+    highlight::Highlighter,
+    prompt,
+};
 use crate::{sys, types};
 use liner::{BasicCompleter, CursorPosition, Event, EventKind};
-use std::{env, io::ErrorKind, mem, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    env,
+    io::{self, ErrorKind, Write},
+    mem,
+    path::PathBuf,
+    rc::Rc,
+};
+use termion::{event::Key, terminal_size};
 
 pub(crate) fn readln(shell: &mut Shell) -> Option<String> {
     let vars_ptr = &shell.variables as *const Variables;
@@ -20,14 +32,56 @@ pub(crate) fn readln(shell: &mut Shell) -> Option<String> {
         // Collect each result into a vector to avoid borrowing issues.
         .collect::<Vec<types::Str>>();
 
+    shell.notifications.print_queued(&shell.background);
+
+    // The status of the repository is only read once while the prompts are generated.
+    git::begin_prompt();
     let prompt = shell.prompt();
+    let rprompt = prompt::rprompt(shell);
+    let transient = prompt::transient_prompt(shell);
+    git::end_prompt();
+    let highlighter = Highlighter::new(shell).map(Rc::new);
+
+    // The right prompt is drawn along with the buffer, so that it is redrawn on every change,
+    // and hidden once the buffer would overlap with it.
+    let decorator: Option<Box<dyn Fn(&str) -> String>> =
+        if highlighter.is_none() && rprompt.is_none() {
+            None
+        } else {
+            let highlighter = highlighter.clone();
+            let prompt_width = prompt::width(prompt.rsplit('\n').next().unwrap_or(""));
+            Some(Box::new(move |line| {
+                let mut output = highlighter
+                    .as_ref()
+                    .map_or_else(|| line.to_owned(), |highlighter| highlighter.highlight(line));
+                if let (Some(rprompt), Ok((columns, _))) = (&rprompt, terminal_size()) {
+                    let used = prompt_width + prompt::width(line);
+                    prompt::right_align(&mut output, used, rprompt, columns as usize);
+                }
+                output
+            }))
+        };
     let vars = &shell.variables;
     let builtins = &shell.builtins;
+    let abbreviations = &shell.abbreviations;
 
     let line = shell.context.as_mut().unwrap().read_line(
-        prompt,
-        None,
+        prompt.clone(),
+        decorator,
         &mut move |Event { editor, kind }| {
+            // Abbreviations are replaced by their expansions before the key is handled.
+            if let EventKind::BeforeKey(Key::Char(character)) = kind {
+                if character == ' ' || character == '\n' {
+                    let line = editor.current_buffer().range(0, editor.cursor());
+                    if let Some((length, expansion)) = abbreviation(&line, abbreviations) {
+                        for _ in 0..length {
+                            let _ = editor.delete_before_cursor();
+                        }
+                        let _ = editor.insert_str_after_cursor(expansion);
+                    }
+                }
+            }
+
             if let EventKind::BeforeComplete = kind {
                 let (words, pos) = editor.get_words_and_cursor_position();
 
@@ -111,6 +165,9 @@ pub(crate) fn readln(shell: &mut Shell) -> Option<String> {
 
     match line {
         Ok(line) => {
+            if let Some(transient) = transient {
+                redraw_transient(&prompt, &transient, &line, highlighter.as_ref().map(|h| &**h));
+            }
             if line.bytes().any(|c| !c.is_ascii_whitespace()) {
                 shell.flags |= flags::UNTERMINATED;
             }
@@ -134,6 +191,47 @@ pub(crate) fn readln(shell: &mut Shell) -> Option<String> {
     }
 }
 
+/// Replaces the prompt and the accepted line in the scrollback with the transient prompt.
+fn redraw_transient(prompt: &str, transient: &str, line: &str, highlighter: Option<&Highlighter>) {
+    let columns = match terminal_size() {
+        Ok((columns, _)) => columns as usize,
+        Err(_) => return,
+    };
+
+    let rows = prompt::rows(&[prompt, line].concat(), columns);
+    let line = highlighter.map_or_else(|| line.to_owned(), |h| h.highlight(line));
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let _ = writeln!(stdout, "\x1b[{}A\r\x1b[J{}{}", rows, transient, line);
+    let _ = stdout.flush();
+}
+
+/// Finds the abbreviation which ends at the cursor, if it was typed in the position of a command,
+/// and returns the number of characters that it spans along with its expansion.
+fn abbreviation<'a>(
+    line: &str,
+    abbreviations: &'a BTreeMap<types::Str, types::Str>,
+) -> Option<(usize, &'a str)> {
+    let is_separator = |c: char| c.is_whitespace() || c == ';' || c == '|' || c == '&';
+    let prefix = line.trim_end_matches(|c: char| !is_separator(c));
+    let word = &line[prefix.len()..];
+    let expansion = abbreviations.get(word)?;
+
+    let prefix = prefix.trim_end();
+    let is_command = prefix.is_empty()
+        || prefix.ends_with(|c: char| c == ';' || c == '|' || c == '&')
+        || prefix.rsplit(char::is_whitespace).next().map_or(false, |keyword| match keyword {
+            "if" | "while" | "and" | "or" | "not" | "!" | "time" | "else" => true,
+            _ => false,
+        });
+
+    if is_command {
+        Some((word.chars().count(), expansion.as_str()))
+    } else {
+        None
+    }
+}
+
 /// Infer if the given filename is actually a partial filename
 fn complete_as_file(current_dir: &PathBuf, filename: &str, index: usize) -> bool {
     let filename = filename.trim();
@@ -154,3 +252,23 @@ fn complete_as_file(current_dir: &PathBuf, filename: &str, index: usize) -> bool
     file.parent().filter(|parent| parent.exists() && parent != current_dir).is_some()))
     // By default assume its not a file
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn abbreviations() {
+        let mut abbreviations = BTreeMap::new();
+        abbreviations.insert(types::Str::from("gco"), types::Str::from("git checkout"));
+
+        assert_eq!(abbreviation("gco", &abbreviations), Some((3, "git checkout")));
+        assert_eq!(abbreviation("  gco", &abbreviations), Some((3, "git checkout")));
+        assert_eq!(abbreviation("ls | gco", &abbreviations), Some((3, "git checkout")));
+        assert_eq!(abbreviation("true && gco", &abbreviations), Some((3, "git checkout")));
+        assert_eq!(abbreviation("if gco", &abbreviations), Some((3, "git checkout")));
+        assert_eq!(abbreviation("echo gco", &abbreviations), None);
+        assert_eq!(abbreviation("gc", &abbreviations), None);
+        assert_eq!(abbreviation("", &abbreviations), None);
+    }
+}
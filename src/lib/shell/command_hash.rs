@@ -0,0 +1,140 @@
+//! Remembers where the external commands which are executed by the shell were found, so that
+//! the directories of the `PATH` are only searched once for each command.
+
+use crate::sys;
+use ion_builtins::test;
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Where a command was found, and the number of times that it has been executed since.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Hashed {
+    pub path: PathBuf,
+    pub hits: usize,
+}
+
+/// The commands which have been found within the `PATH`. The table is emptied whenever the
+/// `PATH` is assigned, even to the same value, and whenever it otherwise differs from the `PATH`
+/// that the table was populated from.
+#[derive(Debug, Default)]
+pub(crate) struct CommandHash {
+    search:   RefCell<String>,
+    commands: RefCell<BTreeMap<String, Hashed>>,
+}
+
+impl CommandHash {
+    /// Finds the command within the directories of `path`, remembering where it was found.
+    /// Names which contain a `/` are paths rather than commands, and are never found.
+    pub(crate) fn find(&self, name: &str, path: &str) -> Option<PathBuf> {
+        self.lookup(name, path, false)
+    }
+
+    /// Finds the command like `find`, counting it as a hit because it is about to be executed.
+    pub(crate) fn hit(&self, name: &str, path: &str) -> Option<PathBuf> {
+        self.lookup(name, path, true)
+    }
+
+    fn lookup(&self, name: &str, path: &str, hit: bool) -> Option<PathBuf> {
+        if name.is_empty() || name.contains('/') {
+            return None;
+        }
+        self.invalidate(path);
+
+        let mut commands = self.commands.borrow_mut();
+        // A command which was removed since it was found is searched for again.
+        if commands.get(name).map_or(true, |hashed| !is_executable(&hashed.path)) {
+            match search(name, path) {
+                // Commands found relative to the working directory may not be there later.
+                Some(found) if found.is_relative() => return Some(found),
+                Some(found) => {
+                    commands.insert(name.into(), Hashed { path: found, hits: 0 });
+                }
+                None => {
+                    commands.remove(name);
+                    return None;
+                }
+            }
+        }
+
+        let hashed = commands.get_mut(name)?;
+        if hit {
+            hashed.hits += 1;
+        }
+        Some(hashed.path.clone())
+    }
+
+    /// Remembers the location of a command, as if it were found there.
+    pub(crate) fn remember(&self, name: &str, path: PathBuf, search: &str) {
+        self.invalidate(search);
+        self.commands.borrow_mut().insert(name.into(), Hashed { path, hits: 0 });
+    }
+
+    /// Forgets the locations of every command.
+    pub(crate) fn clear(&self) { self.commands.borrow_mut().clear(); }
+
+    /// The commands which have been found, in order of their names.
+    pub(crate) fn entries(&self, search: &str) -> Vec<(String, Hashed)> {
+        self.invalidate(search);
+        self.commands.borrow().iter().map(|(name, hashed)| (name.clone(), hashed.clone())).collect()
+    }
+
+    /// Empties the table if the commands were found within a different `PATH`.
+    fn invalidate(&self, path: &str) {
+        let mut search = self.search.borrow_mut();
+        if *search != path {
+            self.commands.borrow_mut().clear();
+            *search = path.into();
+        }
+    }
+}
+
+/// Returns true if the path is a file which may be executed.
+pub(crate) fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).ok().map_or(false, |metadata| metadata.is_file())
+        && test::file_has_execute_permission(path)
+}
+
+/// Searches the directories of the `PATH` for an executable file with the given name.
+fn search(name: &str, path: &str) -> Option<PathBuf> {
+    path.split(sys::PATH_SEPARATOR)
+        .map(|directory| Path::new(directory).join(name))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn executables() {
+        assert_eq!(is_executable(Path::new("testing/executable_file")), true);
+        assert_eq!(is_executable(Path::new("testing/empty_file")), false);
+        assert_eq!(is_executable(Path::new("this-does-not-exist")), false);
+        assert_eq!(is_executable(Path::new("testing")), false);
+    }
+
+    #[test]
+    fn hashing() {
+        let hash = CommandHash::default();
+        let found = hash.find("executable_file", "testing/").unwrap();
+        assert_eq!(found, Path::new("testing/executable_file"));
+        assert_eq!(hash.find("empty_file", "testing/"), None);
+        assert_eq!(hash.find("testing/executable_file", "testing/"), None);
+
+        hash.remember("tool", PathBuf::from("/bin/sh"), "/usr/bin");
+        assert_eq!(hash.hit("tool", "/usr/bin"), Some(PathBuf::from("/bin/sh")));
+        assert_eq!(hash.hit("tool", "/usr/bin"), Some(PathBuf::from("/bin/sh")));
+        let entries = hash.entries("/usr/bin");
+        assert_eq!(entries, vec![("tool".into(), Hashed { path: "/bin/sh".into(), hits: 2 })]);
+
+        // Assigning a different `PATH` forgets every command.
+        assert!(hash.entries("/bin").is_empty());
+        hash.remember("tool", PathBuf::from("/bin/sh"), "/bin");
+        hash.clear();
+        assert!(hash.entries("/bin").is_empty());
+    }
+}
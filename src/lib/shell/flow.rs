@@ -2,11 +2,13 @@ use super::{
     flags::*,
     flow_control::{insert_statement, Case, ElseIf, Function, Statement},
     job_control::JobControl,
+    parallel::Parallel,
     signals,
     status::*,
     Shell,
 };
 use crate::{
+    builtins::times,
     parser::{
         assignments::is_array,
         expand_string, parse_and_validate,
@@ -18,7 +20,7 @@ use crate::{
 };
 use itertools::Itertools;
 use small;
-use std::io::{stdout, Write};
+use std::time::Instant;
 
 macro_rules! handle_signal {
     ($signal:expr) => {
@@ -56,6 +58,7 @@ pub(crate) trait FlowLogic {
         &mut self,
         variables: &[types::Str],
         values: &[small::String],
+        parallel: Option<&str>,
         statements: Vec<Statement>,
     ) -> Condition;
 
@@ -116,8 +119,22 @@ impl FlowLogic for Shell {
         &mut self,
         variables: &[types::Str],
         values: &[small::String],
+        parallel: Option<&str>,
         statements: Vec<Statement>,
     ) -> Condition {
+        // With a limit, the iterations are executed concurrently by forks of the shell.
+        let mut parallel = match parallel {
+            Some(limit) => match expand_string(limit, self, false).join(" ").parse::<usize>() {
+                Ok(limit) if limit > 0 => Some(Parallel::new(limit)),
+                _ => {
+                    eprintln!("ion: for: invalid number of jobs: {}", limit);
+                    self.previous_status = BAD_ARG;
+                    return Condition::NoOp;
+                }
+            },
+            None => None,
+        };
+
         macro_rules! set_vars_then_exec {
             ($chunk:expr, $def:expr) => {
                 for (key, value) in variables.iter().zip($chunk.chain(::std::iter::repeat($def))) {
@@ -126,7 +143,12 @@ impl FlowLogic for Shell {
                     }
                 }
 
-                handle_signal!(self.execute_statements(statements.clone()));
+                match parallel {
+                    Some(ref mut parallel) => {
+                        handle_signal!(parallel.spawn(self, statements.clone()))
+                    }
+                    None => handle_signal!(self.execute_statements(statements.clone())),
+                }
             };
         }
 
@@ -150,7 +172,10 @@ impl FlowLogic for Shell {
             }
         };
 
-        Condition::NoOp
+        match parallel {
+            Some(parallel) => parallel.finish(self),
+            None => Condition::NoOp,
+        }
     }
 
     fn execute_while(
@@ -193,8 +218,11 @@ impl FlowLogic for Shell {
                     return Condition::SigInt;
                 }
             }
-            Statement::For { variables, values, statements } => {
-                if let Condition::SigInt = self.execute_for(&variables, &values, statements) {
+            Statement::For { variables, values, parallel, statements } => {
+                let parallel = parallel.as_ref().map(|limit| limit.as_str());
+                if let Condition::SigInt =
+                    self.execute_for(&variables, &values, parallel, statements)
+                {
                     return Condition::SigInt;
                 }
             }
@@ -232,27 +260,23 @@ impl FlowLogic for Shell {
                 }
             },
             Statement::Time(box_statement) => {
-                let time = ::std::time::Instant::now();
+                let started = Instant::now();
+                let (user_before, system_before) = times::cpu_times();
 
                 let condition = self.execute_statement(*box_statement);
 
-                let duration = time.elapsed();
-                let seconds = duration.as_secs();
-                let nanoseconds = duration.subsec_nanos();
-
-                let stdout = stdout();
-                let mut stdout = stdout.lock();
-                let _ = if seconds > 60 {
-                    writeln!(
-                        stdout,
-                        "real    {}m{:02}.{:09}s",
-                        seconds / 60,
-                        seconds % 60,
-                        nanoseconds
-                    )
-                } else {
-                    writeln!(stdout, "real    {}.{:09}s", seconds, nanoseconds)
-                };
+                // Times are reported on stderr, so that the output of the command is unaffected.
+                let (user, system) = times::cpu_times();
+                let real = started.elapsed();
+                let user = user.checked_sub(user_before).unwrap_or_default();
+                let system = system.checked_sub(system_before).unwrap_or_default();
+                let format = self
+                    .variables
+                    .get::<types::Str>("TIMEFORMAT")
+                    .map_or_else(|| times::DEFAULT_FORMAT.into(), |format| format.to_string());
+                if !format.is_empty() {
+                    eprintln!("{}", times::format_times(&format, real, user, system));
+                }
                 match condition {
                     Condition::Break => return Condition::Break,
                     Condition::Continue => return Condition::Continue,
@@ -485,7 +509,11 @@ fn expand_pipeline(
     let mut statements = Vec::new();
 
     while let Some(item) = item_iter.next() {
-        let possible_alias = shell.variables.get::<types::Alias>(item.job.command.as_ref());
+        let possible_alias = if item.job.direct {
+            None
+        } else {
+            shell.variables.get::<types::Alias>(item.job.command.as_ref())
+        };
         if let Some(alias) = possible_alias {
             statements = StatementSplitter::new(alias.0.as_str()).map(parse_and_validate).collect();
 
@@ -85,6 +85,8 @@ pub(crate) enum Statement {
     For {
         variables:  SmallVec<[types::Str; 4]>,
         values:     Vec<small::String>,
+        /// The limit of iterations which are executed concurrently, if any.
+        parallel:   Option<small::String>,
         statements: Vec<Statement>,
     },
     While {
@@ -429,6 +431,8 @@ impl Function {
         Ok(())
     }
 
+    pub(crate) fn statements(&self) -> &[Statement] { &self.statements }
+
     pub(crate) fn get_description(&self) -> Option<&small::String> { self.description.as_ref() }
 
     pub(crate) fn new(
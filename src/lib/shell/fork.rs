@@ -1,5 +1,5 @@
-use super::{IonError, Shell};
-use crate::sys;
+use super::{pipe_exec::reaper, IonError, Shell};
+use crate::{builtins::times, sys};
 use std::{
     fs::File,
     io,
@@ -9,12 +9,20 @@ use std::{
 pub fn wait_for_child(pid: u32) -> io::Result<u8> {
     loop {
         let mut status = 0;
-        if let Err(errno) = sys::waitpid(pid as i32, &mut status, sys::WUNTRACED) {
-            break if errno == sys::ECHILD {
-                Ok(sys::wexitstatus(status) as u8)
-            } else {
-                Err(io::Error::from_raw_os_error(errno))
-            };
+        match sys::wait4(pid as i32, &mut status, sys::WUNTRACED) {
+            Ok((0, _)) => (),
+            Ok((_, usage)) => {
+                if sys::wifexited(status) || sys::wifsignaled(status) {
+                    times::record_child(usage);
+                }
+            }
+            Err(errno) => {
+                break if errno == sys::ECHILD {
+                    Ok(sys::wexitstatus(status) as u8)
+                } else {
+                    Err(io::Error::from_raw_os_error(errno))
+                };
+            }
         }
     }
 }
@@ -98,7 +106,7 @@ impl<'a> Fork<'a> {
         // be repeated.
         let null_file = File::open(sys::NULL_PATH);
 
-        match unsafe { sys::fork() } {
+        match unsafe { reaper::fork() } {
             Ok(0) => {
                 // Allow the child to handle it's own signal handling.
                 sys::signals::unblock();
@@ -5,6 +5,7 @@ use crate::{
     shell::pipe_exec::PipelineExecution,
     types,
 };
+use small;
 use std::{fmt, fs::File, str};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -21,6 +22,8 @@ pub(crate) struct Job {
     pub args:    types::Array,
     pub kind:    JobKind,
     pub builtin: Option<BuiltinFunction>,
+    /// Whether functions and aliases are bypassed, as for `command name` and `builtin name`.
+    pub direct:  bool,
 }
 
 impl Job {
@@ -33,10 +36,30 @@ impl Job {
         self.args = expanded;
     }
 
-    pub(crate) fn new(args: types::Array, kind: JobKind) -> Self {
+    pub(crate) fn new(mut args: types::Array, kind: JobKind) -> Self {
+        let direct = match bypassed(&args) {
+            Some(skip) => {
+                args = args.drain().skip(skip).collect();
+                true
+            }
+            None => false,
+        };
         let command = args[0].clone();
         let builtin = BUILTINS.get(command.as_ref()).map(|b| b.main);
-        Job { command, args, kind, builtin }
+        Job { command, args, kind, builtin, direct }
+    }
+}
+
+/// If the job is `command name` or `builtin name`, returns the number of arguments which
+/// precede the name that is to be executed directly. The `builtin` builtin itself reports names
+/// which are not builtins, and `command` describes names when given `-v` or `-V`.
+fn bypassed(args: &[small::String]) -> Option<usize> {
+    let skip = if args.get(1).map_or(false, |arg| arg == "--") { 2 } else { 1 };
+    let name = args.get(skip)?;
+    match args[0].as_str() {
+        "command" if skip == 2 || !name.starts_with('-') => Some(skip),
+        "builtin" if BUILTINS.contains_key(name) => Some(skip),
+        _ => None,
     }
 }
 
@@ -1,6 +1,7 @@
 mod assignments;
 pub(crate) mod binary;
 pub(crate) mod colors;
+pub(crate) mod command_hash;
 mod completer;
 pub(crate) mod directory_stack;
 pub(crate) mod escape;
@@ -10,9 +11,11 @@ mod fork;
 pub mod fork_function;
 mod history;
 mod job;
+mod parallel;
 pub(crate) mod pipe_exec;
 pub(crate) mod signals;
 pub mod status;
+mod suggestions;
 pub mod variables;
 
 pub mod flags {
@@ -36,16 +39,17 @@ pub(crate) use self::{
     flow::FlowLogic,
     history::{IgnoreSetting, ShellHistory},
     job::{Job, JobKind},
-    pipe_exec::{foreground, job_control},
+    pipe_exec::{foreground, job_control, reaper},
 };
 
 use self::{
+    command_hash::CommandHash,
     directory_stack::DirectoryStack,
     flags::*,
     flow_control::{FlowControl, Function, FunctionError},
     foreground::ForegroundSignals,
     job_control::{BackgroundProcess, JobControl},
-    pipe_exec::PipelineExecution,
+    pipe_exec::{reaper::Notifications, Coprocess, PipelineExecution},
     status::*,
     variables::{GetVariable, Value, Variables},
 };
@@ -58,11 +62,12 @@ use crate::{
 use itertools::Itertools;
 use liner::Context;
 use std::{
-    fs,
+    collections::BTreeMap,
+    fs::{self, File},
     io::{self, Read, Write},
     iter::FromIterator,
     ops::Deref,
-    path::Path,
+    path::{Path, PathBuf},
     process,
     sync::{atomic::Ordering, Arc, Mutex},
     time::SystemTime,
@@ -103,6 +108,8 @@ pub struct Shell {
     pub previous_status: i32,
     /// The job ID of the previous command sent to the background.
     pub(crate) previous_job: u32,
+    /// The job ID which was the previous job before it, which is referred to by `%-`.
+    pub(crate) prior_job: u32,
     /// Contains all the boolean flags that control shell behavior.
     pub flags: u8,
     /// Contains information on all of the active background processes that are being managed
@@ -110,17 +117,36 @@ pub struct Shell {
     pub(crate) background: Arc<Mutex<Vec<BackgroundProcess>>>,
     /// If set, denotes that this shell is running as a background job.
     pub(crate) is_background_shell: bool,
+    /// Within the forked shell of a background job, reports the process IDs of the job's
+    /// members to the shell which forked it.
+    pub(crate) pid_reporter: Option<File>,
+    /// The process IDs of the members of the pipeline which is currently being executed.
+    pub(crate) member_pids: Vec<u32>,
+    /// Within a forked member of a job, the process group of that job. Commands executed by
+    /// the member join this group, so that the job may be stopped and resumed as a whole.
+    pub(crate) job_pgid: Option<u32>,
     /// Set when a signal is received, this will tell the flow control logic to
     /// abort.
     pub(crate) break_flow: bool,
     // Useful for disabling the execution of the `tcsetpgrp` call.
     pub(crate) is_library: bool,
+    /// Set while a prompt function is evaluated, so that the commands which it executes do not
+    /// take the foreground process group of the terminal away from the line editor.
+    pub(crate) generating_prompt: bool,
     /// When the `fg` command is run, this will be used to communicate with the specified
     /// background process.
     foreground_signals: Arc<ForegroundSignals>,
+    /// State changes of background jobs, which are reported by the reaper.
+    pub(crate) notifications: Arc<Notifications>,
     /// Stores the patterns used to determine whether a command should be saved in the history
     /// or not
     ignore_setting: IgnoreSetting,
+    /// Abbreviations which are expanded within the editor's buffer, rather than at execution.
+    pub(crate) abbreviations: BTreeMap<types::Str, types::Str>,
+    /// The pipes of the coprocesses started by the `coproc` builtin, by name.
+    pub(crate) coprocesses: BTreeMap<types::Str, Coprocess>,
+    /// The locations of the external commands which have been found within the `PATH`.
+    pub(crate) command_hash: CommandHash,
 }
 
 #[derive(Default)]
@@ -249,6 +275,18 @@ impl Shell {
         self.variables.get_str_or_empty(name)
     }
 
+    /// Determines whether commands may be given the foreground process group of the terminal.
+    pub(crate) fn can_take_terminal(&self) -> bool { !self.is_library && !self.generating_prompt }
+
+    /// Determines whether foreground jobs may be stopped and resumed by the user, which is the
+    /// case for the interactive shell that owns the terminal.
+    pub(crate) fn job_control_active(&self) -> bool {
+        self.context.is_some()
+            && !self.is_background_shell
+            && self.job_pgid.is_none()
+            && self.can_take_terminal()
+    }
+
     /// Gets any variable, if it exists within the shell's variable map.
     pub fn get<T>(&self, name: &str) -> Option<T>
     where
@@ -260,6 +298,23 @@ impl Shell {
     /// Sets a variable of `name` with the given `value` in the shell's variable map.
     pub fn set<T: Into<Value>>(&mut self, name: &str, value: T) { self.variables.set(name, value); }
 
+    /// Finds the external command with the given name within the `PATH`, through the hash of
+    /// the commands which have already been found.
+    pub(crate) fn command_path(&self, name: &str) -> Option<PathBuf> {
+        self.command_hash.find(name, &self.get_str_or_empty("PATH"))
+    }
+
+    /// The names of the builtins, functions, and aliases, which are commands without being
+    /// found within the `PATH`.
+    pub(crate) fn command_names(&self) -> impl Iterator<Item = String> + '_ {
+        self.builtins
+            .keys()
+            .iter()
+            .map(|&name| name.to_owned())
+            .chain(self.variables.functions().map(|(name, _)| name.to_string()))
+            .chain(self.variables.aliases().map(|(name, _)| name.to_string()))
+    }
+
     /// Executes a pipeline and returns the final exit status of the pipeline.
     pub(crate) fn run_pipeline(&mut self, pipeline: &mut Pipeline) -> Option<i32> {
         let command_start_time = SystemTime::now();
@@ -282,8 +337,9 @@ impl Shell {
                 Some(self.execute_pipeline(pipeline))
             }
         // Branch else if -> input == shell function and set the exit_status
-        } else if let Some(function) =
-            self.variables.get::<Function>(&pipeline.items[0].job.command)
+        } else if let Some(function) = Some(&pipeline.items[0].job)
+            .filter(|job| !job.direct)
+            .and_then(|job| self.variables.get::<Function>(&job.command))
         {
             if !pipeline.requires_piping() {
                 let args = pipeline.items[0].job.args.deref();
@@ -383,14 +439,23 @@ impl Shell {
             flow_control: FlowControl::default(),
             directory_stack: DirectoryStack::new(),
             previous_job: !0,
+            prior_job: !0,
             previous_status: 0,
             flags: 0,
             background: Arc::new(Mutex::new(Vec::new())),
             is_background_shell: false,
+            pid_reporter: None,
+            member_pids: Vec::new(),
+            job_pgid: None,
             is_library,
+            generating_prompt: false,
             break_flow: false,
             foreground_signals: Arc::new(ForegroundSignals::new()),
+            notifications: Arc::new(Notifications::new()),
             ignore_setting: IgnoreSetting::default(),
+            abbreviations: BTreeMap::new(),
+            coprocesses: BTreeMap::new(),
+            command_hash: CommandHash::default(),
         };
         let ignore_patterns = shell.variables.get("HISTORY_IGNORE").unwrap();
         shell.update_ignore_patterns(&ignore_patterns);
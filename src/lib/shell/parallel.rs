@@ -0,0 +1,178 @@
+//! Executes the iterations of parallel `for` loops within forks of the shell.
+
+use super::{
+    flow::{Condition, FlowLogic},
+    flow_control::Statement,
+    pipe_exec::{reaper, streams::redir},
+    signals,
+    status::*,
+    Shell,
+};
+use crate::{builtins::times, sys};
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, Seek, SeekFrom, Write},
+    os::unix::io::AsRawFd,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::Duration,
+};
+
+/// How often the running iterations are checked upon, in milliseconds, while waiting for one to
+/// finish.
+const INTERVAL: u64 = 10;
+
+/// The highest exit status given to a loop, which is the number of iterations that failed.
+const MAX_FAILURES: usize = 101;
+
+/// Creates an unnamed file in the temporary directory, in which the output of an iteration is
+/// buffered.
+fn buffer() -> io::Result<File> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let path = env::temp_dir().join(format!(
+        "ion-parallel-{}-{}",
+        process::id(),
+        COUNT.fetch_add(1, Ordering::SeqCst)
+    ));
+    let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+    let _ = fs::remove_file(&path);
+    Ok(file)
+}
+
+/// Writes the buffered output of an iteration.
+fn flush(buffer: &mut File, output: &mut dyn Write) {
+    if buffer.seek(SeekFrom::Start(0)).is_ok() {
+        let _ = io::copy(buffer, output);
+        let _ = output.flush();
+    }
+}
+
+/// An iteration of the loop which is being executed by a fork of the shell.
+struct Iteration {
+    pid:    u32,
+    stdout: File,
+    stderr: File,
+}
+
+/// Executes the iterations of a loop concurrently, keeping no more than the limit of iterations
+/// running at once. The output of each iteration is buffered, and written once the iteration
+/// has finished, so that the output of iterations is never interleaved.
+pub(crate) struct Parallel {
+    limit:    usize,
+    running:  Vec<Iteration>,
+    failures: usize,
+}
+
+impl Parallel {
+    /// Forks the shell to execute the statements of an iteration, after waiting for fewer than
+    /// the limit of iterations to be running.
+    pub(crate) fn spawn(&mut self, shell: &mut Shell, statements: Vec<Statement>) -> Condition {
+        while self.running.len() >= self.limit {
+            if let Condition::SigInt = self.wait_any() {
+                return Condition::SigInt;
+            }
+        }
+
+        let (stdout, stderr) = match buffer().and_then(|stdout| Ok((stdout, buffer()?))) {
+            Ok(buffers) => buffers,
+            Err(why) => {
+                eprintln!("ion: parallel for: failed to create output buffer: {}", why);
+                self.failures += 1;
+                return Condition::NoOp;
+            }
+        };
+
+        let _ = io::stdout().flush();
+        let _ = io::stderr().flush();
+        match unsafe { reaper::fork() } {
+            Ok(0) => {
+                let _ = sys::reset_signal(sys::SIGINT);
+                let _ = sys::reset_signal(sys::SIGHUP);
+                let _ = sys::reset_signal(sys::SIGTERM);
+                redir(stdout.as_raw_fd(), sys::STDOUT_FILENO);
+                redir(stderr.as_raw_fd(), sys::STDERR_FILENO);
+
+                // Commands executed by the iteration remain within the shell's process group,
+                // rather than taking the terminal from one another.
+                shell.is_background_shell = true;
+                shell.job_pgid = sys::getpgid(0).ok();
+                shell.coprocesses.clear();
+                shell.execute_statements(statements);
+
+                let _ = io::stdout().flush();
+                let _ = io::stderr().flush();
+                sys::fork_exit(shell.previous_status);
+            }
+            Ok(pid) => self.running.push(Iteration { pid, stdout, stderr }),
+            Err(why) => {
+                eprintln!("ion: parallel for: failed to fork: {}", why);
+                self.failures += 1;
+            }
+        }
+        Condition::NoOp
+    }
+
+    /// Waits for every remaining iteration to finish, and sets the exit status of the loop to
+    /// the number of iterations which failed.
+    pub(crate) fn finish(mut self, shell: &mut Shell) -> Condition {
+        while !self.running.is_empty() {
+            if let Condition::SigInt = self.wait_any() {
+                return Condition::SigInt;
+            }
+        }
+
+        shell.previous_status = self.failures.min(MAX_FAILURES) as i32;
+        shell.variables.set("?", shell.previous_status.to_string());
+        Condition::NoOp
+    }
+
+    /// Waits until any of the running iterations has finished, writing its output. If the
+    /// shell receives a signal, the iterations are terminated.
+    fn wait_any(&mut self) -> Condition {
+        loop {
+            if signals::PENDING.load(Ordering::SeqCst) != 0 {
+                for iteration in self.running.drain(..) {
+                    let _ = sys::kill(iteration.pid, sys::SIGTERM);
+                    let mut status = 0;
+                    let _ = sys::waitpid(iteration.pid as i32, &mut status, 0);
+                }
+                return Condition::SigInt;
+            }
+
+            let failures = &mut self.failures;
+            let finished = self.running.iter().position(|iteration| {
+                let mut status = 0;
+                match sys::wait4(iteration.pid as i32, &mut status, sys::WNOHANG) {
+                    Ok((0, _)) | Err(sys::EINTR) => false,
+                    Ok((_, usage)) if sys::wifexited(status) || sys::wifsignaled(status) => {
+                        times::record_child(usage);
+                        if !sys::wifexited(status) || sys::wexitstatus(status) != SUCCESS {
+                            *failures += 1;
+                        }
+                        true
+                    }
+                    Ok(_) => false,
+                    Err(_) => {
+                        *failures += 1;
+                        true
+                    }
+                }
+            });
+
+            if let Some(index) = finished {
+                let mut iteration = self.running.remove(index);
+                flush(&mut iteration.stdout, &mut io::stdout());
+                flush(&mut iteration.stderr, &mut io::stderr());
+                return Condition::NoOp;
+            }
+
+            thread::sleep(Duration::from_millis(INTERVAL));
+        }
+    }
+
+    pub(crate) fn new(limit: usize) -> Parallel {
+        Parallel { limit, running: Vec::new(), failures: 0 }
+    }
+}
@@ -1,4 +1,5 @@
-use crate::sys;
+use crate::{sys, types};
+use small;
 use smallvec::SmallVec;
 
 /// Ensures that the forked child is given a unique process ID.
@@ -6,14 +7,180 @@ pub(crate) fn create_process_group(pgid: u32) { let _ = sys::setpgid(0, pgid); }
 
 use super::{
     super::{
-        job::{JobKind, RefinedJob},
+        flow_control::{Function, Statement},
+        job::{JobKind, JobVariant, RefinedJob},
         status::*,
         Shell,
     },
+    is_implicit_cd,
     job_control::{JobControl, ProcessState},
-    pipe,
+    pipe, prepare_child, reaper, wait_for_interrupt,
 };
-use std::process::exit;
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    os::unix::io::{AsRawFd, FromRawFd},
+    process::{self, exit},
+};
+
+/// The ends of the pipes of a coprocess which are held by the shell.
+pub(crate) struct Coprocess {
+    /// Writes to the standard input of the coprocess, until it is closed.
+    pub(crate) input:  Option<File>,
+    /// Reads from the standard output of the coprocess.
+    pub(crate) output: File,
+}
+
+/// Builtins which change the state of the shell, and therefore may not be executed by a job.
+const STATEFUL_BUILTINS: &[&str] = &[
+    "abbr", "alias", "bg", "cd", "coproc", "dirs", "disown", "eval", "exec", "exit", "fg", "hash",
+    "history", "jobs", "kill", "popd", "pushd", "set", "source", "suspend", "ulimit", "umask",
+    "unalias", "wait",
+];
+
+/// Scans the statements of functions for the commands that they execute.
+struct Scan<'a> {
+    shell:     &'a Shell,
+    functions: Vec<types::Str>,
+    external:  bool,
+}
+
+impl<'a> Scan<'a> {
+    /// Scans the named function, and the functions that it calls, returning `false` if any of
+    /// them change the state of the shell.
+    fn function(&mut self, name: &str) -> bool {
+        if self.functions.iter().any(|function| function.as_str() == name) {
+            return true;
+        }
+        self.functions.push(name.into());
+        match self.shell.variables.get::<Function>(name) {
+            Some(function) => self.statements(function.statements()),
+            None => true,
+        }
+    }
+
+    fn statements(&mut self, statements: &[Statement]) -> bool {
+        statements.iter().all(|statement| self.statement(statement))
+    }
+
+    fn statement(&mut self, statement: &Statement) -> bool {
+        match statement {
+            Statement::Export(_) => false,
+            Statement::Pipeline(pipeline) => pipeline.items.iter().all(|item| {
+                item.job.args.get(0).map_or(true, |name| self.command(name, item.job.direct))
+            }),
+            Statement::If { expression, success, else_if, failure, .. } => {
+                self.statements(expression)
+                    && self.statements(success)
+                    && else_if.iter().all(|branch| {
+                        self.statements(&branch.expression) && self.statements(&branch.success)
+                    })
+                    && self.statements(failure)
+            }
+            Statement::ElseIf(branch) => {
+                self.statements(&branch.expression) && self.statements(&branch.success)
+            }
+            Statement::For { statements, .. } => self.statements(statements),
+            Statement::While { expression, statements } => {
+                self.statements(expression) && self.statements(statements)
+            }
+            Statement::Match { cases, .. } => {
+                cases.iter().all(|case| self.statements(&case.statements))
+            }
+            Statement::Case(case) => self.statements(&case.statements),
+            Statement::Time(statement)
+            | Statement::And(statement)
+            | Statement::Or(statement)
+            | Statement::Not(statement) => self.statement(statement),
+            _ => true,
+        }
+    }
+
+    fn command(&mut self, name: &str, direct: bool) -> bool {
+        if !direct && self.shell.variables.get::<Function>(name).is_some() {
+            self.function(name)
+        } else if self.shell.builtins.contains_key(name) {
+            !STATEFUL_BUILTINS.contains(&name)
+        } else if !direct
+            && (is_implicit_cd(name) || self.shell.variables.get::<types::Alias>(name).is_some())
+        {
+            false
+        } else {
+            self.external = true;
+            true
+        }
+    }
+}
+
+/// Determines whether a pipeline should be forked into a job of its own, which is the case for
+/// a function which executes external commands without changing the state of the shell.
+pub(crate) fn forks_as_job(shell: &Shell, commands: &[(RefinedJob, JobKind)]) -> bool {
+    match commands {
+        [(RefinedJob { var: JobVariant::Function { name, .. }, .. }, JobKind::Last)] => {
+            let mut scan = Scan { shell, functions: Vec::new(), external: false };
+            scan.function(name) && scan.external
+        }
+        _ => false,
+    }
+}
+
+/// Creates the job which executes the given command, which may be a function, a builtin, or an
+/// external command.
+pub(crate) fn refine(shell: &Shell, args: &[small::String]) -> RefinedJob {
+    let command: types::Array = args.iter().cloned().collect();
+    if shell.variables.get::<Function>(&args[0]).is_some() {
+        RefinedJob::function(args[0].clone(), command)
+    } else if let Some(builtin) = shell.builtins.get(&args[0]) {
+        RefinedJob::builtin(builtin.main, command)
+    } else {
+        RefinedJob::external(args[0].clone(), command)
+    }
+}
+
+/// Forks the shell into a process group of its own which executes the given commands, giving
+/// the group the terminal if `terminal` is set. Returns the process ID of the fork, which leads
+/// the group.
+fn fork_group(
+    shell: &mut Shell,
+    commands: SmallVec<[(RefinedJob, JobKind); 16]>,
+    terminal: bool,
+) -> io::Result<u32> {
+    let pid = unsafe { reaper::fork()? };
+    if pid == 0 {
+        // The child waits until the parent has given it the terminal.
+        prepare_child(true, 0);
+        shell.is_background_shell = true;
+        shell.job_pgid = Some(process::id());
+        shell.coprocesses.clear();
+        sys::fork_exit(pipe(shell, commands, false));
+    }
+
+    let _ = sys::setpgid(pid, pid);
+    if terminal && shell.can_take_terminal() {
+        let _ = sys::tcsetpgrp(0, pid);
+    }
+    let _ = wait_for_interrupt(pid);
+    let _ = sys::kill(pid, sys::SIGCONT);
+    Ok(pid)
+}
+
+/// Forks the shell into a foreground job which executes the given commands, so that the job may
+/// be stopped and resumed as a whole, along with every command that it executes.
+pub(crate) fn fork_job(shell: &mut Shell, commands: SmallVec<[(RefinedJob, JobKind); 16]>) -> i32 {
+    let command_name =
+        commands.iter().map(|(job, _)| job.long()).collect::<Vec<String>>().join(" | ");
+
+    match fork_group(shell, commands, true) {
+        Ok(pid) => {
+            shell.member_pids = vec![pid];
+            shell.watch_foreground(-(pid as i32), &command_name)
+        }
+        Err(why) => {
+            eprintln!("ion: job fork failed: {}", why);
+            FAILURE
+        }
+    }
+}
 
 /// Forks the shell, adding the child to the parent's background list, and executing
 /// the given commands in the child fork.
@@ -23,30 +190,110 @@ pub(crate) fn fork_pipe(
     command_name: String,
     state: ProcessState,
 ) -> i32 {
-    match unsafe { sys::fork() } {
-        Ok(0) => {
-            shell.is_background_shell = true;
-            let _ = sys::reset_signal(sys::SIGINT);
-            let _ = sys::reset_signal(sys::SIGHUP);
-            let _ = sys::reset_signal(sys::SIGTERM);
-            let _ = sys::close(sys::STDIN_FILENO);
+    match fork_background(shell, commands, command_name, state) {
+        Ok(_) => SUCCESS,
+        Err(why) => {
+            eprintln!("ion: background fork failed: {}", why);
+            exit(FAILURE);
+        }
+    }
+}
 
-            // This ensures that the child fork has a unique PGID.
-            create_process_group(0);
+/// Forks a background job which executes the given commands, returning the process ID of the
+/// fork. Jobs which are not disowned are added to the background list.
+fn fork_background(
+    shell: &mut Shell,
+    commands: SmallVec<[(RefinedJob, JobKind); 16]>,
+    command_name: String,
+    state: ProcessState,
+) -> io::Result<u32> {
+    // The members of the job are forked by the child, which reports their process IDs.
+    let reports = if state != ProcessState::Empty {
+        sys::pipe2(sys::O_CLOEXEC | sys::O_NONBLOCK)
+            .ok()
+            .map(|(reader, writer)| (sys::raise_fd(reader), sys::raise_fd(writer)))
+    } else {
+        None
+    };
 
-            // After execution of it's commands, exit with the last command's status.
-            sys::fork_exit(pipe(shell, commands, false));
+    let pid = unsafe { reaper::fork()? };
+    if pid == 0 {
+        shell.is_background_shell = true;
+        // The job must not hold the pipes of coprocesses open.
+        shell.coprocesses.clear();
+        if let Some((reader, writer)) = reports {
+            let _ = sys::close(reader);
+            shell.pid_reporter = Some(unsafe { File::from_raw_fd(writer) });
         }
-        Ok(pid) => {
-            if state != ProcessState::Empty {
-                // The parent process should add the child fork's PID to the background.
-                shell.send_to_background(pid, state, command_name);
+        let _ = sys::reset_signal(sys::SIGINT);
+        let _ = sys::reset_signal(sys::SIGHUP);
+        let _ = sys::reset_signal(sys::SIGTERM);
+        let _ = sys::close(sys::STDIN_FILENO);
+        // A job started by a prompt function must not hold the function's output open.
+        if shell.generating_prompt {
+            if let Ok(null) = OpenOptions::new().write(true).open(sys::NULL_PATH) {
+                let _ = sys::dup2(null.as_raw_fd(), sys::STDOUT_FILENO);
             }
-            SUCCESS
-        }
-        Err(why) => {
-            eprintln!("ion: background fork failed: {}", why);
-            exit(FAILURE);
         }
+
+        // This ensures that the child fork has a unique PGID.
+        create_process_group(0);
+
+        // After execution of it's commands, exit with the last command's status.
+        sys::fork_exit(pipe(shell, commands, false));
+    }
+
+    let reports = reports.map(|(reader, writer)| {
+        let _ = sys::close(writer);
+        unsafe { File::from_raw_fd(reader) }
+    });
+
+    if state != ProcessState::Empty {
+        // The parent process should add the child fork's PID to the background.
+        shell.send_to_background(pid, state, command_name, Vec::new(), reports);
     }
+    Ok(pid)
+}
+
+/// Forks the shell into a process group of its own which executes the given command, so that
+/// the command may be supervised by a builtin. Returns the process ID of the fork.
+pub(crate) fn fork_command(shell: &mut Shell, args: &[small::String]) -> io::Result<u32> {
+    let mut commands = SmallVec::new();
+    commands.push((refine(shell, args), JobKind::Last));
+    // A command which is supervised within a job must not take the terminal from that job.
+    let terminal = !shell.is_background_shell && shell.job_pgid.is_none();
+    fork_group(shell, commands, terminal)
+}
+
+/// Forks a background job which executes the given command as the coprocess `name`, with the
+/// standard input and output of the command connected to pipes whose other ends are held by the
+/// shell. A coprocess which previously had the same name has its pipes closed. Returns the
+/// process ID of the job.
+///
+/// The shell's ends of the pipes are closed on exec, so they may only be used by the shell
+/// itself, through redirections and builtins, and are not inherited by external commands.
+pub(crate) fn fork_coprocess(
+    shell: &mut Shell,
+    name: &str,
+    args: &[small::String],
+) -> io::Result<u32> {
+    let (reader, writer) = sys::pipe2(sys::O_CLOEXEC)?;
+    let writer = sys::raise_fd(writer);
+    let (stdin, input) = unsafe { (File::from_raw_fd(reader), File::from_raw_fd(writer)) };
+    let (reader, writer) = sys::pipe2(sys::O_CLOEXEC)?;
+    let reader = sys::raise_fd(reader);
+    let (output, stdout) = unsafe { (File::from_raw_fd(reader), File::from_raw_fd(writer)) };
+
+    let mut job = refine(shell, args);
+    job.stdin(stdin);
+    job.stdout(stdout);
+
+    // The shell's ends are stored before forking, so that they are closed within the job.
+    shell.coprocesses.insert(name.into(), Coprocess { input: Some(input), output });
+    let mut commands = SmallVec::new();
+    commands.push((job, JobKind::Last));
+    fork_background(shell, commands, args.join(" "), ProcessState::Running).map_err(|why| {
+        shell.coprocesses.remove(name);
+        why
+    })
 }
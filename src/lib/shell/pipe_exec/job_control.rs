@@ -1,12 +1,15 @@
 use super::{
     super::{signals, status::*, Shell},
-    foreground::{BackgroundResult, ForegroundSignals},
+    foreground::BackgroundResult,
+    reaper,
 };
-use crate::sys;
+use crate::{builtins::times, sys, types};
 use std::{
-    fmt, process,
+    fmt,
+    fs::File,
+    process,
     sync::{Arc, Mutex},
-    thread::{sleep, spawn},
+    thread::sleep,
     time::Duration,
 };
 
@@ -21,6 +24,8 @@ pub(crate) fn set_foreground_as(pid: u32) {
 pub trait JobControl {
     /// Waits for background jobs to finish before returning.
     fn wait_for_background(&mut self);
+    /// Waits for any of the given background jobs to finish, returning the ID of that job.
+    fn wait_for_any(&mut self, jobs: &[u32]) -> u32;
     /// Takes a background tasks's PID and whether or not it needs to be continued; resumes the
     /// task
     /// and sets it as the foreground process. Once the task exits or stops, the exit status
@@ -31,7 +36,14 @@ pub trait JobControl {
     fn handle_signal(&self, signal: i32) -> bool;
     fn background_send(&self, signal: i32);
     fn watch_foreground(&mut self, pid: i32, command: &str) -> i32;
-    fn send_to_background(&mut self, child: u32, state: ProcessState, command: String);
+    fn send_to_background(
+        &mut self,
+        child: u32,
+        state: ProcessState,
+        command: String,
+        members: Vec<u32>,
+        reports: Option<File>,
+    );
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -52,17 +64,37 @@ impl fmt::Display for ProcessState {
     }
 }
 
+/// The greatest number of exit statuses of finished jobs which are kept for `wait`. Once there
+/// are as many, the slot of the lowest numbered finished job is reused, and its status is lost.
+const KEPT_STATUSES: usize = 1024;
+
 pub(crate) fn add_to_background(
     processes: &Arc<Mutex<Vec<BackgroundProcess>>>,
     pid: u32,
     state: ProcessState,
     command: String,
+    members: Vec<u32>,
 ) -> u32 {
     let mut processes = processes.lock().unwrap();
-    match (*processes).iter().position(|x| x.state == ProcessState::Empty) {
+    // The slots of jobs which finished are only reused once their exit statuses are collected,
+    // or forgotten.
+    let finished: Vec<usize> =
+        (0..processes.len()).filter(|&id| processes[id].state == ProcessState::Empty).collect();
+    let slot = finished
+        .iter()
+        .cloned()
+        .find(|&id| processes[id].status.is_none())
+        .or_else(|| finished.first().cloned().filter(|_| finished.len() >= KEPT_STATUSES));
+    match slot {
         Some(id) => {
-            (*processes)[id] =
-                BackgroundProcess { pid, ignore_sighup: false, state, name: command };
+            (*processes)[id] = BackgroundProcess {
+                pid,
+                ignore_sighup: false,
+                state,
+                name: command,
+                members,
+                status: None,
+            };
             id as u32
         }
         None => {
@@ -72,6 +104,8 @@ pub(crate) fn add_to_background(
                 ignore_sighup: false,
                 state,
                 name: command,
+                members,
+                status: None,
             });
             njobs as u32
         }
@@ -82,12 +116,17 @@ pub(crate) fn add_to_background(
 /// A background process is a process that is attached to, but not directly managed
 /// by the shell. The shell will only retain information about the process, such
 /// as the process ID, state that the process is in, and the command that the
-/// process is executing.
+/// process is executing. Once the process has exited, its exit status is retained
+/// until it is collected by the `wait` builtin, or in an interactive shell, until the
+/// completion of the job has been reported.
 pub struct BackgroundProcess {
     pub pid:           u32,
     pub ignore_sighup: bool,
     pub state:         ProcessState,
     pub name:          String,
+    /// The process IDs of each process that was spawned for the job's pipelines.
+    pub members:       Vec<u32>,
+    pub status:        Option<i32>,
 }
 
 impl JobControl for Shell {
@@ -102,24 +141,41 @@ impl JobControl for Shell {
         }
     }
 
-    fn send_to_background(&mut self, pid: u32, state: ProcessState, command: String) {
-        // Increment the `Arc` counters so that these fields can be moved into
-        // the upcoming background thread.
-        let processes = self.background.clone();
-        let fg_signals = self.foreground_signals.clone();
-
+    fn send_to_background(
+        &mut self,
+        pid: u32,
+        state: ProcessState,
+        command: String,
+        members: Vec<u32>,
+        reports: Option<File>,
+    ) {
         // Add the process to the background list, and mark the job's ID as
         // the previous job in the shell (in case fg/bg is executed w/ no args).
-        let njob = add_to_background(&processes, pid, state, command);
+        let njob = add_to_background(&self.background, pid, state, command, members);
+        if njob != self.previous_job {
+            self.prior_job = self.previous_job;
+        }
         self.previous_job = njob;
         eprintln!("ion: bg [{}] {}", njob, pid);
 
-        // Spawn a background thread that will monitor the progress of the
-        // background process, updating it's state changes until it finally
-        // exits.
-        let _ = spawn(move || {
-            watch_background(&fg_signals, &processes, pid, njob as usize);
-        });
+        // Have the reaper monitor the progress of the background process, updating
+        // it's state changes until it finally exits. Jobs which run for longer than the
+        // threshold also notify the terminal once they are done.
+        let notify_after = self
+            .variables
+            .get::<types::Str>("JOB_NOTIFY_THRESHOLD")
+            .and_then(|seconds| seconds.parse::<f64>().ok())
+            .filter(|&seconds| seconds >= 0.0)
+            .map(|seconds| Duration::from_millis((seconds * 1000.0) as u64));
+        reaper::watch(
+            self.background.clone(),
+            self.foreground_signals.clone(),
+            self.notifications.clone(),
+            pid,
+            njob as usize,
+            notify_after,
+            reports,
+        );
     }
 
     /// Send a kill signal to all running background tasks.
@@ -154,7 +210,7 @@ impl JobControl for Shell {
 
         loop {
             let mut status = 0;
-            match waitpid(pid, &mut status, WUNTRACED) {
+            let (child, usage) = match wait4(pid, &mut status, WUNTRACED) {
                 Err(errno) => match errno {
                     ECHILD if signaled == 0 => break exit_status,
                     ECHILD => break signaled,
@@ -163,9 +219,18 @@ impl JobControl for Shell {
                         break FAILURE;
                     }
                 },
-                Ok(0) => (),
-                Ok(_) if wifexited(status) => exit_status = wexitstatus(status),
-                Ok(pid) if wifsignaled(status) => {
+                Ok(result) => result,
+            };
+
+            // The times of a child are counted once it exits, as `time` reports them.
+            if child != 0 && (wifexited(status) || wifsignaled(status)) {
+                times::record_child(usage);
+            }
+
+            match child {
+                0 => (),
+                _ if wifexited(status) => exit_status = wexitstatus(status),
+                pid if wifsignaled(status) => {
                     let signal = wtermsig(status);
                     if signal == SIGPIPE {
                         continue;
@@ -186,16 +251,21 @@ impl JobControl for Shell {
                     }
                     signaled = 128 + signal as i32;
                 }
-                Ok(pid) if wifstopped(status) => {
+                // The members of a job are stopped and resumed along with the job itself.
+                _ if wifstopped(status) && self.job_pgid.is_some() => (),
+                pid if wifstopped(status) => {
+                    let members = self.member_pids.clone();
                     self.send_to_background(
                         pid.abs() as u32,
                         ProcessState::Stopped,
                         command.into(),
+                        members,
+                        None,
                     );
                     self.break_flow = true;
                     break 128 + wstopsig(status);
                 }
-                Ok(_) => (),
+                _ => (),
             }
         }
     }
@@ -212,9 +282,34 @@ impl JobControl for Shell {
         }
     }
 
+    /// Waits until any of the given jobs has completed, listening for signals in the same
+    /// manner as `wait_for_background`, and returns the ID of the job which completed.
+    fn wait_for_any(&mut self, jobs: &[u32]) -> u32 {
+        loop {
+            let finished = self.background.lock().unwrap().iter().enumerate().position(
+                |(njob, process)| {
+                    process.state == ProcessState::Empty && jobs.contains(&(njob as u32))
+                },
+            );
+
+            if let Some(njob) = finished {
+                return njob as u32;
+            }
+
+            if let Some(signal) = signals::SignalHandler.find(|&s| s != sys::SIGTSTP) {
+                self.background_send(signal);
+                self.exit(get_signal_code(signal));
+            }
+            sleep(Duration::from_millis(100));
+        }
+    }
+
     fn set_bg_task_in_foreground(&self, pid: u32, cont: bool) -> i32 {
         // Pass the TTY to the background job
-        set_foreground_as(pid);
+        let terminal = self.can_take_terminal();
+        if terminal {
+            set_foreground_as(pid);
+        }
         // Signal the background thread that is waiting on this process to stop waiting.
         self.foreground_signals.signal_to_grab(pid);
         // Resume the background task, if needed.
@@ -233,88 +328,60 @@ impl JobControl for Shell {
             }
         };
         // Have the shell reclaim the TTY
-        set_foreground_as(process::id());
+        if terminal {
+            set_foreground_as(process::id());
+        }
         status
     }
 }
 
 use crate::sys::{
-    kill, strerror, waitpid, wcoredump, wexitstatus, wifcontinued, wifexited, wifsignaled,
-    wifstopped, wstopsig, wtermsig, ECHILD, SIGINT, SIGPIPE, WCONTINUED, WNOHANG, WUNTRACED,
+    kill, strerror, wait4, wcoredump, wexitstatus, wifexited, wifsignaled, wifstopped,
+    wstopsig, wtermsig, ECHILD, SIGINT, SIGPIPE, WUNTRACED,
 };
 
-const OPTS: i32 = WUNTRACED | WCONTINUED | WNOHANG;
-
-pub(crate) fn watch_background(
-    fg: &Arc<ForegroundSignals>,
-    processes: &Arc<Mutex<Vec<BackgroundProcess>>>,
-    pgid: u32,
-    njob: usize,
-) {
-    let (mut fg_was_grabbed, mut status);
-    let mut exit_status = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    macro_rules! get_process {
-        (| $ident:ident | $func:expr) => {
-            let mut processes = processes.lock().unwrap();
-            let $ident = &mut processes.iter_mut().nth(njob).unwrap();
-            $func
+    #[test]
+    fn reuses_collected_slots() {
+        let processes = Arc::new(Mutex::new(Vec::new()));
+        let add = |pid| {
+            add_to_background(&processes, pid, ProcessState::Running, "job".into(), Vec::new())
         };
-    }
-
-    loop {
-        fg_was_grabbed = fg.was_grabbed(pgid);
-        status = 0;
-        match waitpid(-(pgid as i32), &mut status, OPTS) {
-            Err(errno) if errno == ECHILD => {
-                if !fg_was_grabbed {
-                    eprintln!("ion: ([{}] {}) exited with {}", njob, pgid, status);
-                }
-
-                get_process!(|process| {
-                    process.state = ProcessState::Empty;
-                    if fg_was_grabbed {
-                        fg.reply_with(exit_status as i8);
-                    }
-                });
+        assert_eq!(add(1), 0);
+        assert_eq!(add(2), 1);
 
-                break;
-            }
-            Err(errno) => {
-                eprintln!("ion: ([{}] {}) errored: {}", njob, pgid, errno);
-
-                get_process!(|process| {
-                    process.state = ProcessState::Empty;
-                    if fg_was_grabbed {
-                        fg.errored();
-                    }
-                });
-
-                break;
-            }
-            Ok(0) => (),
-            Ok(_) if wifexited(status) => exit_status = wexitstatus(status),
-            Ok(_) if wifstopped(status) => {
-                if !fg_was_grabbed {
-                    eprintln!("ion: ([{}] {}) Stopped", njob, pgid);
-                }
+        // The first job finished, but its exit status has yet to be collected by `wait`.
+        {
+            let mut processes = processes.lock().unwrap();
+            processes[0].state = ProcessState::Empty;
+            processes[0].status = Some(3);
+        }
+        assert_eq!(add(3), 2);
+        assert_eq!(processes.lock().unwrap()[0].status, Some(3));
 
-                get_process!(|process| {
-                    if fg_was_grabbed {
-                        fg.reply_with(TERMINATED as i8);
-                    }
-                    process.state = ProcessState::Stopped;
-                });
-            }
-            Ok(_) if wifcontinued(status) => {
-                if !fg_was_grabbed {
-                    eprintln!("ion: ([{}] {}) Running", njob, pgid);
-                }
+        processes.lock().unwrap()[0].status.take();
+        assert_eq!(add(4), 0);
+        assert_eq!(processes.lock().unwrap()[0].pid, 4);
+    }
 
-                get_process!(|process| process.state = ProcessState::Running);
-            }
-            Ok(_) => (),
+    #[test]
+    fn caps_kept_statuses() {
+        let processes = Arc::new(Mutex::new(Vec::new()));
+        for pid in 0..KEPT_STATUSES as u32 + 10 {
+            let njob = add_to_background(
+                &processes,
+                pid + 1,
+                ProcessState::Running,
+                "job".into(),
+                Vec::new(),
+            );
+            let mut processes = processes.lock().unwrap();
+            processes[njob as usize].state = ProcessState::Empty;
+            processes[njob as usize].status = Some(0);
         }
-        sleep(Duration::from_millis(100));
+        assert_eq!(processes.lock().unwrap().len(), KEPT_STATUSES);
     }
 }
@@ -9,10 +9,13 @@ pub mod foreground;
 mod fork;
 pub mod job_control;
 mod pipes;
+pub(crate) mod reaper;
 pub mod streams;
 
+pub(crate) use self::fork::{fork_command, fork_coprocess, Coprocess};
+
 use self::{
-    fork::fork_pipe,
+    fork::{fork_job, fork_pipe, forks_as_job, refine},
     job_control::{JobControl, ProcessState},
     pipes::TeePipe,
     streams::{duplicate_streams, redir, redirect_streams},
@@ -24,6 +27,7 @@ use super::{
     job::{JobVariant, RefinedJob, TeeItem},
     signals::{self, SignalHandler},
     status::*,
+    suggestions,
     JobKind, Shell,
 };
 use crate::{
@@ -40,6 +44,8 @@ use std::{
     os::unix::io::{AsRawFd, FromRawFd, RawFd},
     path::Path,
     process::{self, exit},
+    thread,
+    time::Duration,
 };
 
 type RefinedItem = (RefinedJob, JobKind, Vec<Redirection>, Vec<Input>);
@@ -408,8 +414,10 @@ impl PipelineExecution for Shell {
         stdout: &Option<File>,
         stderr: &Option<File>,
     ) -> i32 {
+        let path = self.command_hash.hit(name, &self.get_str_or_empty("PATH"));
         let result = sys::fork_and_exec(
             name,
+            path.as_ref().and_then(|path| path.to_str()),
             args,
             if let Some(ref f) = *stdin { Some(f.as_raw_fd()) } else { None },
             if let Some(ref f) = *stdout { Some(f.as_raw_fd()) } else { None },
@@ -420,17 +428,41 @@ impl PipelineExecution for Shell {
 
         match result {
             Ok(pid) => {
-                let _ = sys::setpgid(pid, pid);
-                let _ = sys::tcsetpgrp(0, pid);
+                self.member_pids.clear();
+                record_member(self, pid);
+                let watched = match self.job_pgid {
+                    // Commands executed by a member of a job remain within the job's group.
+                    Some(_) => pid as i32,
+                    None => {
+                        let _ = sys::setpgid(pid, pid);
+                        if self.can_take_terminal() {
+                            let _ = sys::tcsetpgrp(0, pid);
+                        }
+                        -(pid as i32)
+                    }
+                };
                 let _ = wait_for_interrupt(pid);
                 let _ = sys::kill(pid, sys::SIGCONT);
-                self.watch_foreground(-(pid as i32), "")
+                self.watch_foreground(watched, "")
             }
             Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
-                if !command_not_found(self, &name) {
-                    eprintln!("ion: command not found: {}", name);
+                if command_not_found(self, &name) {
+                    return NO_SUCH_COMMAND;
+                }
+                match suggestions::not_found(self, &name, true) {
+                    // The correction is executed with the streams of the original command.
+                    Some(correction) => {
+                        let command: Vec<small::String> = iter::once(correction.into())
+                            .chain(args.iter().map(|arg| arg.as_ref().into()))
+                            .collect();
+                        let mut job = refine(self, &command);
+                        job.stdin = stdin.as_ref().and_then(|file| file.try_clone().ok());
+                        job.stdout = stdout.as_ref().and_then(|file| file.try_clone().ok());
+                        job.stderr = stderr.as_ref().and_then(|file| file.try_clone().ok());
+                        job.exec(self)
+                    }
+                    None => NO_SUCH_COMMAND,
                 }
-                NO_SUCH_COMMAND
             }
             Err(ref err) => {
                 eprintln!("ion: command exec error: {}", err);
@@ -601,12 +633,14 @@ impl PipelineExecution for Shell {
         for item in pipeline.items.drain(..) {
             let PipeItem { mut job, outputs, inputs } = item;
             let refined = {
-                if is_implicit_cd(&job.args[0]) {
+                if !job.direct && is_implicit_cd(&job.args[0]) {
                     RefinedJob::builtin(
                         builtins::builtin_cd,
                         iter::once("cd".into()).chain(job.args.drain()).collect(),
                     )
-                } else if self.variables.get::<Function>(job.args[0].as_str()).is_some() {
+                } else if !job.direct
+                    && self.variables.get::<Function>(job.args[0].as_str()).is_some()
+                {
                     RefinedJob::function(job.args[0].clone(), job.args.drain().collect())
                 } else if let Some(builtin) = job.builtin {
                     RefinedJob::builtin(builtin, job.args.drain().collect())
@@ -625,6 +659,21 @@ impl PipelineExecution for Shell {
         // and a boolean representing whether it should be disowned is stored here.
         let possible_background_name =
             gen_background_string(&pipeline, self.flags & PRINT_COMMS != 0);
+        // An `exec` without a command redirects the shell itself, rather than a child.
+        if builtins::exec::redirects_shell(pipeline) {
+            if self.flags & NO_EXEC != 0 {
+                return SUCCESS;
+            }
+            return match builtins::exec::redirect_shell(&mut pipeline.items[0]) {
+                Ok(()) => SUCCESS,
+                Err(why) => {
+                    if !why.is_empty() {
+                        eprintln!("ion: exec: {}", why);
+                    }
+                    FAILURE
+                }
+            };
+        }
         // Generates commands for execution, differentiating between external and
         // builtin commands.
         let piped_commands = match self.generate_commands(pipeline) {
@@ -650,6 +699,11 @@ impl PipelineExecution for Shell {
                 command_name,
                 if disown { ProcessState::Empty } else { ProcessState::Running },
             ),
+            // Functions which execute external commands are forked into a job of their own,
+            // so that they may be stopped along with those commands.
+            None if self.job_control_active() && forks_as_job(self, &piped_commands) => {
+                fork_job(self, piped_commands)
+            }
             None => {
                 // While active, the SIGTTOU signal will be ignored.
                 let _sig_ignore = SignalHandler::new();
@@ -657,7 +711,7 @@ impl PipelineExecution for Shell {
                 // Execute each command in the pipeline, giving each command the foreground.
                 let exit_status = pipe(self, piped_commands, foreground);
                 // Set the shell as the foreground process again to regain the TTY.
-                if foreground && !self.is_library {
+                if foreground && self.can_take_terminal() {
                     let _ = sys::tcsetpgrp(0, process::id());
                 }
                 exit_status
@@ -685,7 +739,10 @@ pub(crate) fn pipe(
                 // descriptors that are created by sys::pipe.
                 let remember: SmallVec<[RefinedJob; 16]> = SmallVec::new();
                 let mut block_child = true;
-                let (mut pgid, mut last_pid, mut current_pid) = (0, 0, 0);
+                // Within a job, the members of the pipeline join the job's process group.
+                let (mut pgid, mut last_pid, mut current_pid) =
+                    (shell.job_pgid.unwrap_or(0), 0, 0);
+                shell.member_pids.clear();
 
                 // Append jobs until all piped jobs are running
                 while let Some((mut child, ckind)) = commands.next() {
@@ -750,7 +807,9 @@ pub(crate) fn pipe(
 
                     ext_stdio_pipes = None;
 
-                    if set_process_group(&mut pgid, current_pid) && foreground && !shell.is_library
+                    if set_process_group(&mut pgid, current_pid)
+                        && foreground
+                        && shell.can_take_terminal()
                     {
                         let _ = sys::tcsetpgrp(0, pgid);
                     }
@@ -812,14 +871,17 @@ fn spawn_proc(
     current_pid: &mut u32,
     pgid: u32,
 ) -> i32 {
+    let previous_pid = *current_pid;
     let stdin = &mut cmd.stdin;
     let stdout = &mut cmd.stdout;
     let stderr = &mut cmd.stderr;
     match cmd.var {
         JobVariant::External { ref mut name, ref mut args } => {
             let args: Vec<&str> = args.iter().skip(1).map(|x| x as &str).collect();
+            let path = shell.command_hash.hit(name, &shell.get_str_or_empty("PATH"));
             let mut result = sys::fork_and_exec(
                 name,
+                path.as_ref().and_then(|path| path.to_str()),
                 &args,
                 stdin.as_mut().map(|f| f.as_raw_fd()),
                 stdout.as_mut().map(|f| f.as_raw_fd()),
@@ -835,7 +897,8 @@ fn spawn_proc(
                 }
                 Err(ref mut err) if err.kind() == io::ErrorKind::NotFound => {
                     if !command_not_found(shell, &name) {
-                        eprintln!("ion: command not found: {}", name);
+                        // Members of a pipeline are not corrected, as the pipeline is running.
+                        suggestions::not_found(shell, &name, false);
                     }
                 }
                 Err(ref mut err) => {
@@ -852,7 +915,10 @@ fn spawn_proc(
                 last_pid,
                 current_pid,
                 pgid,
-                |stdout, stderr, stdin| shell.exec_builtin(main, args, stdout, stderr, stdin),
+                |stdout, stderr, stdin| {
+                    join_job(shell);
+                    shell.exec_builtin(main, args, stdout, stderr, stdin)
+                },
             );
         }
         JobVariant::Function { ref mut name, ref mut args } => {
@@ -864,7 +930,10 @@ fn spawn_proc(
                 last_pid,
                 current_pid,
                 pgid,
-                |stdout, stderr, stdin| shell.exec_function(name, &args, stdout, stderr, stdin),
+                |stdout, stderr, stdin| {
+                    join_job(shell);
+                    shell.exec_function(name, &args, stdout, stderr, stdin)
+                },
             );
         }
         JobVariant::Cat { ref mut sources } => {
@@ -892,9 +961,50 @@ fn spawn_proc(
             );
         }
     }
+
+    if *current_pid != previous_pid {
+        record_member(shell, *current_pid);
+    }
     SUCCESS
 }
 
+/// Records the process ID of a member of the pipeline, which is also reported to the parent
+/// shell if this shell is executing a background job.
+fn record_member(shell: &mut Shell, pid: u32) {
+    shell.member_pids.push(pid);
+    let result = match shell.pid_reporter {
+        Some(ref mut reporter) => report_member(reporter, pid),
+        None => return,
+    };
+    if let Err(why) = result {
+        eprintln!("ion: unable to report the process ID of a job's member: {}", why);
+        shell.pid_reporter = None;
+    }
+}
+
+/// Writes the process ID to the pipe which the reaper reads the members of a job from. As the
+/// pipe does not block, a full pipe is waited upon until the reaper has drained it.
+fn report_member(reporter: &mut File, pid: u32) -> io::Result<()> {
+    // Process IDs are reported in little-endian order, as four bytes, which are written to the
+    // pipe atomically.
+    let bytes = [pid as u8, (pid >> 8) as u8, (pid >> 16) as u8, (pid >> 24) as u8];
+    loop {
+        match reporter.write(&bytes) {
+            Ok(written) if written == bytes.len() => return Ok(()),
+            Ok(_) => return Err(io::Error::new(io::ErrorKind::WriteZero, "short write")),
+            Err(ref why) if why.kind() == io::ErrorKind::Interrupted => (),
+            Err(ref why) if why.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(10))
+            }
+            Err(why) => return Err(why),
+        }
+    }
+}
+
+/// Within the forked member of a pipeline, has the commands that the member executes join the
+/// pipeline's process group, rather than creating groups of their own.
+fn join_job(shell: &mut Shell) { shell.job_pgid = sys::getpgid(0).ok(); }
+
 // TODO: Integrate this better within the RefinedJob type.
 fn fork_exec_internal<F>(
     stdout: &mut Option<File>,
@@ -908,7 +1018,7 @@ fn fork_exec_internal<F>(
 ) where
     F: FnMut(&mut Option<File>, &mut Option<File>, &mut Option<File>) -> i32,
 {
-    match unsafe { sys::fork() } {
+    match unsafe { reaper::fork() } {
         Ok(0) => {
             prepare_child(block_child, pgid);
 
@@ -0,0 +1,509 @@
+//! Contains the reaper, which is a single thread that collects the state changes of every
+//! background job whenever the shell receives a `SIGCHLD` signal, rather than each job being
+//! polled by a thread of its own.
+
+use super::{
+    super::{signals, status::*},
+    foreground::ForegroundSignals,
+    job_control::{BackgroundProcess, ProcessState},
+};
+use crate::sys::{
+    self, strerror, waitpid, wcoredump, wexitstatus, wifcontinued, wifexited, wifsignaled,
+    wifstopped, wtermsig, ECHILD, WCONTINUED, WNOHANG, WUNTRACED,
+};
+use std::{
+    fs::File,
+    io::{self, ErrorKind, Read, Write},
+    mem,
+    os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    process, thread,
+    time::{Duration, Instant},
+};
+
+const OPTS: i32 = WUNTRACED | WCONTINUED | WNOHANG;
+
+lazy_static! {
+    /// Every background job which has not yet exited.
+    static ref WATCHED: Mutex<Vec<Watched>> = Mutex::new(Vec::new());
+}
+
+/// The write end of the pipe which wakes the reaper, or `!0` if it has not been started.
+static WAKE: AtomicUsize = AtomicUsize::new(!0);
+/// The ID of the process which started the reaper, as forked shells must start their own.
+static REAPER_PID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug)]
+/// Background job state changes which are to be reported to the user.
+///
+/// Changes are reported as soon as they occur in immediate mode, which is enabled by `set -b`
+/// and for non-interactive shells. Otherwise, they are queued until the next prompt.
+///
+/// An interactive shell forgets the exit status of a job once its completion has been reported,
+/// as bash does, so that the job's slot in the job table may be reused.
+pub(crate) struct Notifications {
+    immediate:   AtomicBool,
+    interactive: AtomicBool,
+    /// Each message, along with the number and process group of the job that it completes.
+    queue:       Mutex<Vec<(String, Option<(usize, u32)>)>>,
+}
+
+impl Notifications {
+    pub(crate) fn set_immediate(&self, immediate: bool) {
+        self.immediate.store(immediate, Ordering::SeqCst);
+    }
+
+    pub(crate) fn set_interactive(&self, interactive: bool) {
+        self.interactive.store(interactive, Ordering::SeqCst);
+    }
+
+    fn push(&self, message: String) {
+        if self.immediate.load(Ordering::SeqCst) {
+            eprintln!("{}", message);
+        } else {
+            self.queue.lock().unwrap().push((message, None));
+        }
+    }
+
+    /// Reports the completion of a job, returning whether its exit status should be kept
+    /// within the job table until the report is printed, or until it is collected by `wait`.
+    fn push_exit(&self, message: String, njob: usize, pgid: u32) -> bool {
+        if self.immediate.load(Ordering::SeqCst) {
+            eprintln!("{}", message);
+            !self.interactive.load(Ordering::SeqCst)
+        } else {
+            self.queue.lock().unwrap().push((message, Some((njob, pgid))));
+            true
+        }
+    }
+
+    /// Prints each of the changes which were queued since the last time that this was called.
+    /// Within an interactive shell, the exit statuses of the jobs which were reported to have
+    /// completed are forgotten.
+    pub(crate) fn print_queued(&self, processes: &Mutex<Vec<BackgroundProcess>>) {
+        let queue = mem::replace(&mut *self.queue.lock().unwrap(), Vec::new());
+        let interactive = self.interactive.load(Ordering::SeqCst);
+        for (message, completed) in queue {
+            eprintln!("{}", message);
+            if let (true, Some((njob, pgid))) = (interactive, completed) {
+                let mut processes = processes.lock().unwrap();
+                if let Some(process) = processes
+                    .get_mut(njob)
+                    .filter(|p| p.pid == pgid && p.state == ProcessState::Empty)
+                {
+                    process.status = None;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn new() -> Notifications {
+        Notifications {
+            immediate:   AtomicBool::new(true),
+            interactive: AtomicBool::new(false),
+            queue:       Mutex::new(Vec::new()),
+        }
+    }
+}
+
+/// A background job that is watched by the reaper, along with the shell state which it updates.
+struct Watched {
+    processes:     Arc<Mutex<Vec<BackgroundProcess>>>,
+    fg:            Arc<ForegroundSignals>,
+    notifications: Arc<Notifications>,
+    pgid:          u32,
+    njob:          usize,
+    started:       Instant,
+    /// Jobs which take at least this long also send a notification to the terminal.
+    notify_after:  Option<Duration>,
+    exit_status:   i32,
+    /// The signal which terminated the job, and whether it dumped core.
+    signal:        Option<(i32, bool)>,
+    /// Receives the process IDs of the job's members from the shell which spawns them.
+    reports:       Option<File>,
+}
+
+impl Watched {
+    /// Applies the change to the job's entry in the job table, as long as the entry has not
+    /// since been reused by another job.
+    fn update<F: FnOnce(&mut BackgroundProcess)>(&self, change: F) {
+        let mut processes = self.processes.lock().unwrap();
+        if let Some(process) = processes.get_mut(self.njob).filter(|p| p.pid == self.pgid) {
+            change(process);
+        }
+    }
+
+    /// Records the process IDs of members which were spawned since they were last collected,
+    /// and forgets those which have since exited, so that a job which keeps executing commands
+    /// does not accumulate them.
+    fn collect_members(&mut self) {
+        let mut members = Vec::new();
+        if let Some(ref mut reports) = self.reports {
+            let mut buffer = [0u8; 256];
+            // Each process ID is written atomically, so reads never return a partial ID.
+            while let Ok(read) = reports.read(&mut buffer) {
+                if read == 0 {
+                    break;
+                }
+                for pid in buffer[..read].chunks_exact(4) {
+                    members.push(
+                        u32::from(pid[0])
+                            | u32::from(pid[1]) << 8
+                            | u32::from(pid[2]) << 16
+                            | u32::from(pid[3]) << 24,
+                    );
+                }
+            }
+        }
+
+        let pgid = self.pgid;
+        self.update(|process| {
+            process.members.extend_from_slice(&members);
+            // Members are reaped by the job rather than the shell, so a member which has left
+            // the job's process group is taken to have exited.
+            process.members.retain(|&pid| sys::getpgid(pid).ok() == Some(pgid));
+        });
+    }
+
+    /// The command that the job is executing.
+    fn command(&self) -> String {
+        let processes = self.processes.lock().unwrap();
+        let process = processes.get(self.njob).filter(|p| p.pid == self.pgid);
+        process.map_or_else(String::new, |p| p.name.clone())
+    }
+
+    /// Reports that the job has exited, and notifies the terminal if the job ran for long enough.
+    /// Returns whether the job's exit status should be kept.
+    fn report_exit(&self) -> bool {
+        let elapsed = self.started.elapsed();
+        let message = format!(
+            "[{}] {} {}",
+            self.njob,
+            describe_exit(self.exit_status, self.signal, elapsed),
+            self.command()
+        );
+
+        let long = self.notify_after.map_or(false, |after| elapsed >= after);
+        if long && sys::isatty(sys::STDERR_FILENO) {
+            // The notification is sent immediately, regardless of when the message is printed.
+            let _ = write!(io::stderr(), "\x1b]9;{}\x07", message);
+        }
+
+        self.notifications.push_exit(message, self.njob, self.pgid)
+    }
+
+    /// Marks the job as having exited. Its exit status is kept within the job table until the
+    /// job's completion has been reported, and for non-interactive shells, until it is
+    /// collected by `wait`.
+    fn finish<F: FnOnce(&Self) -> bool>(&self, status: i32, report: F) {
+        // The status is stored before the job is reported, so that printing a queued report
+        // always finds the status that it is to forget.
+        self.update(|process| {
+            process.state = ProcessState::Empty;
+            process.status = Some(status);
+        });
+        if !report(self) {
+            self.update(|process| process.status = None);
+        }
+    }
+
+    /// Collects every pending state change of the job's processes, returning `false` once
+    /// all of them have exited.
+    fn reap(&mut self) -> bool {
+        let (njob, pgid) = (self.njob, self.pgid);
+        loop {
+            let fg_was_grabbed = self.fg.was_grabbed(pgid);
+            let mut status = 0;
+            match waitpid(-(pgid as i32), &mut status, OPTS) {
+                Err(errno) if errno == ECHILD => {
+                    if fg_was_grabbed {
+                        // A job in the foreground has its status collected by `fg`.
+                        let (fg, exit_status) = (&self.fg, self.exit_status);
+                        self.update(|process| {
+                            process.state = ProcessState::Empty;
+                            fg.reply_with(exit_status as i8);
+                        });
+                    } else {
+                        self.finish(self.exit_status, Self::report_exit);
+                    }
+
+                    return false;
+                }
+                Err(errno) => {
+                    if fg_was_grabbed {
+                        let fg = &self.fg;
+                        self.update(|process| {
+                            process.state = ProcessState::Empty;
+                            fg.errored();
+                        });
+                    } else {
+                        self.finish(TERMINATED, |job| {
+                            let message = format!(
+                                "[{}] Errored ({}) {}",
+                                njob,
+                                strerror(errno),
+                                job.command()
+                            );
+                            job.notifications.push_exit(message, njob, pgid)
+                        });
+                    }
+
+                    return false;
+                }
+                Ok(0) => return true,
+                Ok(_) if wifexited(status) => {
+                    self.exit_status = wexitstatus(status);
+                    self.signal = None;
+                }
+                Ok(_) if wifsignaled(status) => {
+                    self.exit_status = 128 + wtermsig(status);
+                    self.signal = Some((wtermsig(status), wcoredump(status)));
+                }
+                Ok(_) if wifstopped(status) => {
+                    if !fg_was_grabbed {
+                        self.notifications.push(format!("[{}] Stopped {}", njob, self.command()));
+                    }
+
+                    let fg = &self.fg;
+                    self.update(|process| {
+                        if fg_was_grabbed {
+                            fg.reply_with(TERMINATED as i8);
+                        }
+                        process.state = ProcessState::Stopped;
+                    });
+                }
+                Ok(_) if wifcontinued(status) => {
+                    if !fg_was_grabbed {
+                        self.notifications.push(format!("[{}] Running {}", njob, self.command()));
+                    }
+
+                    self.update(|process| process.state = ProcessState::Running);
+                }
+                Ok(_) => (),
+            }
+        }
+    }
+}
+
+/// Describes how a job ended, such as `Done (exit 3, 12.4s)` or `Killed (SIGKILL)`.
+fn describe_exit(status: i32, signal: Option<(i32, bool)>, elapsed: Duration) -> String {
+    let (signal, core_dumped) = match signal {
+        Some(signal) => signal,
+        None if status == 0 => return format!("Done ({})", format_duration(elapsed)),
+        None => return format!("Done (exit {}, {})", status, format_duration(elapsed)),
+    };
+
+    let name = signals::name(signal);
+    let description = match name {
+        Some("HUP") => "Hangup",
+        Some("INT") => "Interrupted",
+        Some("QUIT") => "Quit",
+        Some("ILL") => "Illegal instruction",
+        Some("ABRT") => "Aborted",
+        Some("BUS") => "Bus error",
+        Some("FPE") => "Floating point exception",
+        Some("KILL") => "Killed",
+        Some("SEGV") => "Segmentation fault",
+        Some("PIPE") => "Broken pipe",
+        Some("ALRM") => "Alarm clock",
+        Some("TERM") => "Terminated",
+        _ => "Signaled",
+    };
+
+    let signal = name.map_or_else(|| signal.to_string(), |name| ["SIG", name].concat());
+    if core_dumped {
+        format!("{} ({}, core dumped)", description, signal)
+    } else {
+        format!("{} ({})", description, signal)
+    }
+}
+
+/// Formats the elapsed time of a job, such as `12.4s`, `3m05s`, or `1h20m`.
+fn format_duration(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs < 60 {
+        format!("{}.{}s", secs, elapsed.subsec_millis() / 100)
+    } else if secs < 3600 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h{:02}m", secs / 3600, secs % 3600 / 60)
+    }
+}
+
+/// Records the process IDs of the members of every background job, which are reported
+/// asynchronously by the shells that execute them.
+pub(crate) fn collect_members() {
+    for job in WATCHED.lock().unwrap().iter_mut() {
+        job.collect_members();
+    }
+}
+
+/// Wakes the reaper, so that it checks each job for changes in state.
+fn wake() {
+    let fd = WAKE.load(Ordering::SeqCst);
+    if fd != !0 {
+        // The file must not be closed when it is dropped, as the reaper still owns it.
+        let mut writer = unsafe { File::from_raw_fd(fd as RawFd) };
+        // The pipe never blocks, as this is called by the signal handler. A full pipe already
+        // wakes the reaper, so a write which would block is ignored.
+        let _ = writer.write(&[0]);
+        writer.into_raw_fd();
+    }
+}
+
+extern "C" fn handle_sigchld(_: i32) { wake(); }
+
+/// Spawns the reaper, and installs the `SIGCHLD` handler which wakes it.
+fn start() {
+    // Jobs which were inherited from the parent of a forked shell are not its children.
+    WATCHED.lock().unwrap().clear();
+
+    let (reader, writer) = match sys::pipe2(sys::O_CLOEXEC | sys::O_NONBLOCK) {
+        Ok((reader, writer)) => (sys::raise_fd(reader), sys::raise_fd(writer)),
+        Err(why) => {
+            eprintln!("ion: unable to create pipe for the job reaper: {}", why);
+            return;
+        }
+    };
+
+    WAKE.store(writer as usize, Ordering::SeqCst);
+    if let Err(why) = sys::signal(sys::SIGCHLD, handle_sigchld) {
+        eprintln!("ion: unable to install SIGCHLD handler: {}", why);
+    }
+
+    let _ = thread::Builder::new().name("reaper".into()).spawn(move || {
+        // The shell blocks `SIGCHLD`, so the reaper must accept it in order to be woken even
+        // when no other thread does.
+        sys::signals::unblock_child();
+
+        let mut reader = unsafe { File::from_raw_fd(reader) };
+        let mut buffer = [0; 64];
+        loop {
+            {
+                let mut watched = WATCHED.lock().unwrap();
+                let mut index = 0;
+                while index < watched.len() {
+                    watched[index].collect_members();
+                    if watched[index].reap() {
+                        index += 1;
+                    } else {
+                        watched.swap_remove(index);
+                    }
+                }
+            }
+
+            match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(ref why) if why.kind() == ErrorKind::Interrupted => (),
+                // Once the pipe has been drained, the reaper waits until it is woken again,
+                // checking each job at least once a second regardless.
+                Err(ref why) if why.kind() == ErrorKind::WouldBlock => {
+                    match sys::poll_input(reader.as_raw_fd(), Duration::from_secs(1)) {
+                        Ok(_) => (),
+                        Err(ref why) if why.kind() == ErrorKind::Interrupted => (),
+                        Err(_) => thread::sleep(Duration::from_millis(25)),
+                    }
+                }
+                Err(why) => {
+                    eprintln!("ion: job reaper stopped: {}", why);
+                    break;
+                }
+            }
+        }
+    });
+}
+
+/// Forks the shell while the reaper is between passes over the jobs.
+///
+/// While reaping, the reaper holds the list of watched jobs, the job table, and the streams that
+/// it prints to. As the reaper's thread does not exist within the child, a lock which it held
+/// during the fork would never be released there, so the list is held by the calling thread
+/// instead, and released by both the parent and the child after the fork.
+pub(crate) unsafe fn fork() -> io::Result<u32> {
+    let watched = WATCHED.lock().unwrap();
+    let result = sys::fork();
+    drop(watched);
+    result
+}
+
+/// Has the reaper watch a job which was sent to the background, until each of its processes
+/// have exited.
+pub(crate) fn watch(
+    processes: Arc<Mutex<Vec<BackgroundProcess>>>,
+    fg: Arc<ForegroundSignals>,
+    notifications: Arc<Notifications>,
+    pgid: u32,
+    njob: usize,
+    notify_after: Option<Duration>,
+    reports: Option<File>,
+) {
+    let pid = process::id() as usize;
+    if REAPER_PID.swap(pid, Ordering::SeqCst) != pid {
+        start();
+    }
+
+    WATCHED.lock().unwrap().push(Watched {
+        processes,
+        fg,
+        notifications,
+        pgid,
+        njob,
+        started: Instant::now(),
+        notify_after,
+        exit_status: 0,
+        signal: None,
+        reports,
+    });
+
+    // The job may have changed state before it was watched, in which case its `SIGCHLD`
+    // would have been missed.
+    wake();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::ShellBuilder;
+
+    #[test]
+    fn interactive_shells_reuse_reported_jobs() {
+        let mut shell = ShellBuilder::new().as_library();
+        shell.notifications.set_interactive(true);
+        shell.notifications.set_immediate(false);
+
+        for _ in 0..20 {
+            shell.execute_command(&"true &").unwrap();
+            let start = Instant::now();
+            while shell.notifications.queue.lock().unwrap().is_empty() {
+                assert!(start.elapsed() < Duration::from_secs(5));
+                thread::sleep(Duration::from_millis(5));
+            }
+            shell.notifications.print_queued(&shell.background);
+        }
+        assert_eq!(shell.background.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(format_duration(Duration::from_millis(12_450)), "12.4s");
+        assert_eq!(format_duration(Duration::from_secs(185)), "3m05s");
+        assert_eq!(format_duration(Duration::from_secs(4800)), "1h20m");
+    }
+
+    #[test]
+    fn exits() {
+        let elapsed = Duration::from_millis(12_400);
+        let (kill, segv) = (signals::from_name("KILL"), signals::from_name("SEGV"));
+        assert_eq!(describe_exit(0, None, elapsed), "Done (12.4s)");
+        assert_eq!(describe_exit(3, None, elapsed), "Done (exit 3, 12.4s)");
+        assert_eq!(describe_exit(137, kill.map(|s| (s, false)), elapsed), "Killed (SIGKILL)");
+        assert_eq!(
+            describe_exit(139, segv.map(|s| (s, true)), elapsed),
+            "Segmentation fault (SIGSEGV, core dumped)"
+        );
+    }
+}
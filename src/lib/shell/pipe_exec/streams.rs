@@ -14,19 +14,18 @@ pub(crate) fn redir(old: RawFd, new: RawFd) {
 
 /// Duplicates STDIN, STDOUT, and STDERR; in that order; and returns them as `File`s.
 /// Why, you ask? A simple safety mechanism to ensure that the duplicated FDs are closed
-/// when dropped.
+/// when dropped. The duplicates are numbered above the descriptors of scripts, so that they
+/// are not replaced by an `exec` before they are restored.
 pub(crate) fn duplicate_streams() -> io::Result<(Option<File>, File, File)> {
+    let duplicate = |fd| sys::dup(fd).map(|fd| unsafe { File::from_raw_fd(sys::raise_fd(fd)) });
     // STDIN may have been closed for a background shell, so it is ok if it cannot be duplicated.
-    let stdin = sys::dup(sys::STDIN_FILENO).ok().map(|fd| unsafe { File::from_raw_fd(fd) });
+    let stdin = duplicate(sys::STDIN_FILENO).ok();
 
-    sys::dup(sys::STDOUT_FILENO)
-        .map(|fd| unsafe { File::from_raw_fd(fd) })
+    duplicate(sys::STDOUT_FILENO)
         .map(|stdout| (stdin, stdout))
         // And then meld stderr alongside stdin and stdout
         .and_then(|(stdin, stdout)| {
-            sys::dup(sys::STDERR_FILENO)
-                .map(|fd| unsafe { File::from_raw_fd(fd) })
-                .map(|stderr| (stdin, stdout, stderr))
+            duplicate(sys::STDERR_FILENO).map(|stderr| (stdin, stdout, stderr))
         })
 }
 
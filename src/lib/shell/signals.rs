@@ -15,6 +15,22 @@ pub const SIGINT: u8 = 1;
 pub const SIGHUP: u8 = 2;
 pub const SIGTERM: u8 = 4;
 
+/// Parses the name or number of a signal, with or without the `SIG` prefix.
+pub(crate) fn from_name(name: &str) -> Option<i32> {
+    if let Ok(signal) = name.parse::<i32>() {
+        return if signal == 0 || self::name(signal).is_some() { Some(signal) } else { None };
+    }
+
+    let name = name.to_ascii_uppercase();
+    let name = if name.starts_with("SIG") { &name[3..] } else { &name[..] };
+    sys::SIGNALS.iter().find(|&&(candidate, _)| candidate == name).map(|&(_, signal)| signal)
+}
+
+/// Obtains the name of a signal, without the `SIG` prefix.
+pub(crate) fn name(signal: i32) -> Option<&'static str> {
+    sys::SIGNALS.iter().find(|&&(_, candidate)| candidate == signal).map(|&(name, _)| name)
+}
+
 /// Suspends a given process by it's process ID.
 pub(crate) fn suspend(pid: u32) { let _ = sys::killpg(pid, sys::SIGSTOP); }
 
@@ -49,3 +65,19 @@ impl Iterator for SignalHandler {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signal_names() {
+        assert_eq!(from_name("TERM"), Some(sys::SIGTERM));
+        assert_eq!(from_name("sigterm"), Some(sys::SIGTERM));
+        assert_eq!(from_name("SIGHUP"), Some(sys::SIGHUP));
+        assert_eq!(from_name(&sys::SIGINT.to_string()), Some(sys::SIGINT));
+        assert_eq!(from_name("0"), Some(0));
+        assert_eq!(from_name("NOTASIGNAL"), None);
+        assert_eq!(name(sys::SIGCONT), Some("CONT"));
+    }
+}
@@ -0,0 +1,124 @@
+//! Suggests the commands which may have been meant when a command is not found, and optionally
+//! offers to execute the closest of them instead.
+
+use super::Shell;
+use crate::{sys, types};
+use std::io::{self, Write};
+
+/// The greatest number of suggestions which are given for a command.
+const SUGGESTIONS: usize = 3;
+
+/// The number of insertions, deletions, substitutions, and transpositions of adjacent characters
+/// which are required to turn one name into the other, so that `gti` is a single edit from `git`.
+fn distance(source: &str, target: &str) -> usize {
+    let source: Vec<char> = source.chars().collect();
+    let target: Vec<char> = target.chars().collect();
+
+    let mut rows = vec![vec![0; target.len() + 1]; source.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=source.len() {
+        for j in 1..=target.len() {
+            let cost = if source[i - 1] == target[j - 1] { 0 } else { 1 };
+            let mut edits =
+                (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && source[i - 1] == target[j - 2] && source[i - 2] == target[j - 1] {
+                edits = edits.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = edits;
+        }
+    }
+    rows[source.len()][target.len()]
+}
+
+/// The candidates which are within a few edits of the command, closest first. Longer names are
+/// allowed more edits, so that short names are not matched by nearly every other short name.
+fn closest<I: IntoIterator<Item = String>>(command: &str, candidates: I) -> Vec<String> {
+    let tolerance = (command.chars().count() / 3).max(1).min(3);
+    let mut matches: Vec<(usize, String)> = candidates
+        .into_iter()
+        .map(|candidate| (distance(command, &candidate), candidate))
+        .filter(|&(edits, _)| edits != 0 && edits <= tolerance)
+        .collect();
+    matches.sort();
+    matches.dedup();
+    matches.into_iter().take(SUGGESTIONS).map(|(_, candidate)| candidate).collect()
+}
+
+/// The builtins, functions, aliases, and hashed executables which may have been meant.
+fn suggestions(shell: &Shell, command: &str) -> Vec<String> {
+    let hashed = shell.command_hash.entries(&shell.get_str_or_empty("PATH"));
+    let candidates = shell.command_names().chain(hashed.into_iter().map(|(name, _)| name));
+    closest(command, candidates)
+}
+
+/// Reports that the command was not found, along with the commands which may have been meant.
+///
+/// If `AUTOCORRECT` is set to `1` within an interactive session, and the closest suggestion is
+/// not an alias, the user is asked whether to execute that command instead. The correction is
+/// returned if the user accepts it.
+pub(crate) fn not_found(shell: &Shell, command: &str, correctable: bool) -> Option<String> {
+    eprintln!("ion: command not found: {}", command);
+    let suggestions = suggestions(shell, command);
+    if suggestions.is_empty() {
+        return None;
+    }
+    eprintln!("ion: did you mean: {}?", suggestions.join(", "));
+
+    let correction = &suggestions[0];
+    let interactive = shell.context.is_some() && sys::isatty(sys::STDIN_FILENO);
+    if !correctable
+        || !interactive
+        || "1" != &*shell.get_str_or_empty("AUTOCORRECT")
+        || shell.variables.get::<types::Alias>(correction).is_some()
+    {
+        return None;
+    }
+
+    eprint!("ion: correct '{}' to '{}'? [y/N] ", command, correction);
+    let _ = io::stderr().flush();
+    let mut answer = String::new();
+    match io::stdin().read_line(&mut answer) {
+        Ok(_) if ["y", "yes"].contains(&answer.trim().to_lowercase().as_str()) => {
+            Some(correction.clone())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        assert_eq!(distance("git", "git"), 0);
+        assert_eq!(distance("gti", "git"), 1);
+        assert_eq!(distance("sl", "ls"), 1);
+        assert_eq!(distance("ech", "echo"), 1);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn closest_candidates() {
+        let candidates = || {
+            ["echo", "exec", "exit", "eval", "git", "grep", "python", "ls", "ln"]
+                .iter()
+                .map(|&name| name.to_owned())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(closest("ecoh", candidates()), vec!["echo"]);
+        assert_eq!(closest("exot", candidates()), vec!["exit"]);
+        assert_eq!(closest("lz", candidates()), vec!["ln", "ls"]);
+        assert_eq!(closest("pyhton", candidates()), vec!["python"]);
+        assert_eq!(closest("gti", candidates()), vec!["git"]);
+        assert!(closest("xyz", candidates()).is_empty());
+        assert!(closest("git", candidates()).is_empty());
+    }
+}
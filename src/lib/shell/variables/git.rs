@@ -0,0 +1,1068 @@
+//! The `git` namespace, which reads the state of the repository in the current directory
+//! directly from the files within its git directory, so that a prompt does not need to fork and
+//! execute `git` in order to display the branch, upstream divergence, or a dirty flag.
+
+use hashbrown::HashMap;
+use miniz_oxide::inflate::decompress_to_vec_zlib;
+use std::{
+    cell::{Cell, RefCell},
+    collections::BinaryHeap,
+    env,
+    ffi::OsStr,
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom},
+    os::unix::{
+        ffi::{OsStrExt, OsStringExt},
+        fs::MetadataExt,
+    },
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::SystemTime,
+};
+
+/// The fields that are supported by the `git` namespace.
+const FIELDS: &[&str] = &["ahead", "behind", "branch", "commit", "dirty", "head", "upstream"];
+
+/// Symbolic references are followed up to this depth, as git itself does.
+const MAX_SYMREF_DEPTH: usize = 5;
+
+/// Deltas within a pack are resolved up to this depth.
+const MAX_DELTA_DEPTH: usize = 64;
+
+/// The maximum number of commits that are visited to compute the upstream divergence.
+const MAX_COMMITS: usize = 10_000;
+
+/// The modification time, size, and contents of the index of a pack.
+type CachedIndex = (SystemTime, u64, Rc<Vec<u8>>);
+
+thread_local! {
+    /// Set while a prompt is being generated, during which the status is cached.
+    static CACHING: Cell<bool> = Cell::new(false);
+    static CACHE: RefCell<Option<(PathBuf, Option<Rc<Status>>)>> = RefCell::new(None);
+    /// The indexes of the packs of the last repository, along with the modification time and
+    /// size of each, so that they are only read again once they have changed.
+    static PACK_INDEXES: RefCell<HashMap<PathBuf, CachedIndex>> = RefCell::new(HashMap::new());
+}
+
+/// Caches the status of the repository until `end_prompt` is called, so that a prompt which
+/// refers to several fields of the namespace only reads the repository once.
+pub(crate) fn begin_prompt() { CACHING.with(|caching| caching.set(true)); }
+
+/// Discards the cached status, so that the next prompt reflects any changes to the repository.
+pub(crate) fn end_prompt() {
+    CACHING.with(|caching| caching.set(false));
+    CACHE.with(|cache| *cache.borrow_mut() = None);
+}
+
+/// Retrieves a field of the `git` namespace for the repository of the current directory.
+pub(crate) fn get(field: &str) -> Option<String> {
+    if !FIELDS.contains(&field) {
+        return None;
+    }
+
+    let directory = env::current_dir().ok()?;
+    let status = CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if let Some((ref cached, ref status)) = *cache {
+            if *cached == directory {
+                return status.clone();
+            }
+        }
+
+        let status = Repository::discover(&directory).and_then(Status::new).map(Rc::new);
+        if CACHING.with(Cell::get) {
+            *cache = Some((directory, status.clone()));
+        }
+        status
+    })?;
+
+    status.field(field)
+}
+
+/// The locations of a repository's git directory and work tree.
+struct Repository {
+    /// Contains `HEAD` and the index of the work tree.
+    git_dir:    PathBuf,
+    /// Contains the refs and objects, which are shared between linked work trees.
+    common_dir: PathBuf,
+    work_tree:  PathBuf,
+}
+
+impl Repository {
+    /// Searches the directory and its ancestors for a repository.
+    fn discover(directory: &Path) -> Option<Repository> {
+        for work_tree in directory.ancestors() {
+            let dot_git = work_tree.join(".git");
+            let git_dir = if dot_git.is_dir() {
+                dot_git
+            } else if dot_git.is_file() {
+                // Linked work trees and submodules point to their git directory instead.
+                let contents = fs::read_to_string(&dot_git).ok()?;
+                let contents = contents.trim_end();
+                if !contents.starts_with("gitdir: ") {
+                    return None;
+                }
+                work_tree.join(&contents[8..])
+            } else {
+                continue;
+            };
+
+            let common_dir = match fs::read_to_string(git_dir.join("commondir")) {
+                Ok(common_dir) => git_dir.join(common_dir.trim_end()),
+                Err(_) => git_dir.clone(),
+            };
+
+            return Some(Repository { git_dir, common_dir, work_tree: work_tree.to_owned() });
+        }
+
+        None
+    }
+
+    /// Resolves a reference to the commit it points to, following symbolic references.
+    fn resolve(&self, reference: &str) -> Option<String> {
+        let mut reference = reference.to_owned();
+        for _ in 0..MAX_SYMREF_DEPTH {
+            let value = self.read_ref(&reference)?;
+            if value.starts_with("ref: ") {
+                reference = value[5..].to_owned();
+            } else {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn read_ref(&self, reference: &str) -> Option<String> {
+        // Refs are shared between work trees, whereas `HEAD` belongs to each work tree.
+        let loose = if reference.starts_with("refs/") {
+            self.common_dir.join(reference)
+        } else {
+            self.git_dir.join(reference)
+        };
+
+        match fs::read_to_string(loose) {
+            Ok(value) => Some(value.trim().to_owned()),
+            Err(_) => self.packed_ref(reference),
+        }
+    }
+
+    fn packed_ref(&self, reference: &str) -> Option<String> {
+        let packed = fs::read_to_string(self.common_dir.join("packed-refs")).ok()?;
+        packed
+            .lines()
+            // Comments, and the peeled values of annotated tags, are skipped.
+            .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+            .filter_map(|line| {
+                let mut fields = line.splitn(2, ' ');
+                Some((fields.next()?, fields.next()?))
+            })
+            .find(|&(_, name)| name.trim_end() == reference)
+            .map(|(commit, _)| commit.to_owned())
+    }
+
+    /// Finds the upstream of a branch in the config, returning its name and commit.
+    fn upstream(&self, branch: &str) -> Option<(String, String)> {
+        let config = fs::read_to_string(self.common_dir.join("config")).ok()?;
+        let section = ["[branch \"", branch, "\"]"].concat();
+
+        let (mut remote, mut merge) = (None, None);
+        let mut in_section = false;
+        for line in config.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_section = line == section;
+            } else if in_section {
+                let mut pair = line.splitn(2, '=').map(str::trim);
+                match (pair.next(), pair.next()) {
+                    (Some("remote"), Some(value)) => remote = Some(value),
+                    (Some("merge"), Some(value)) => merge = Some(value),
+                    _ => (),
+                }
+            }
+        }
+
+        let (remote, merge) = (remote?, merge?);
+        let branch = if merge.starts_with("refs/heads/") { &merge[11..] } else { merge };
+        let (name, reference) = if remote == "." {
+            (branch.to_owned(), merge.to_owned())
+        } else {
+            ([remote, "/", branch].concat(), ["refs/remotes/", remote, "/", branch].concat())
+        };
+
+        self.resolve(&reference).map(|commit| (name, commit))
+    }
+
+    /// Counts the commits which are only reachable from `local`, and those which are only
+    /// reachable from `upstream`, by walking both histories from the newest commit backwards,
+    /// until only commits that are reachable from both remain to be visited.
+    fn ahead_behind(&self, local: &str, upstream: &str) -> Option<(usize, usize)> {
+        const LOCAL: u8 = 1;
+        const UPSTREAM: u8 = 2;
+        const BOTH: u8 = LOCAL | UPSTREAM;
+
+        if local == upstream {
+            return Some((0, 0));
+        }
+
+        let objects = Objects::new(self.common_dir.join("objects"));
+        // The flags of each commit, its parents, and whether it is waiting in the queue.
+        let mut commits: HashMap<String, (u8, Vec<String>, bool)> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+
+        for &(id, flag) in &[(local, LOCAL), (upstream, UPSTREAM)] {
+            let commit = objects.commit(id)?;
+            queue.push((commit.time, id.to_owned()));
+            commits.insert(id.to_owned(), (flag, commit.parents, true));
+        }
+
+        // The number of queued commits which are not yet known to be reachable from both.
+        let mut pending = 2;
+        let (mut ahead, mut behind) = (0, 0);
+        let mut visited = 0;
+        while pending != 0 {
+            visited += 1;
+            if visited > MAX_COMMITS {
+                return None;
+            }
+
+            let (_, id) = queue.pop()?;
+            let (flag, parents) = {
+                let entry = commits.get_mut(&id)?;
+                entry.2 = false;
+                (entry.0, entry.1.clone())
+            };
+            match flag {
+                LOCAL => ahead += 1,
+                UPSTREAM => behind += 1,
+                _ => (),
+            }
+            if flag != BOTH {
+                pending -= 1;
+            }
+
+            for parent in parents {
+                if let Some(entry) = commits.get_mut(&parent) {
+                    if entry.2 && entry.0 != BOTH && entry.0 | flag == BOTH {
+                        pending -= 1;
+                    }
+                    entry.0 |= flag;
+                    continue;
+                }
+
+                let commit = objects.commit(&parent)?;
+                queue.push((commit.time, parent.clone()));
+                commits.insert(parent, (flag, commit.parents, true));
+                if flag != BOTH {
+                    pending += 1;
+                }
+            }
+        }
+
+        Some((ahead, behind))
+    }
+
+    /// Compares the size and modification time of each file in the index with the work tree.
+    ///
+    /// Untracked files, and changes which have already been staged, are not considered.
+    fn is_dirty(&self) -> Option<bool> {
+        let index = fs::read(self.git_dir.join("index")).ok()?;
+        if index.len() < 12 || &index[..4] != b"DIRC" {
+            return None;
+        }
+
+        let version = be32(&index[4..8]);
+        let entries = be32(&index[8..12]);
+        let mut offset = 12;
+        let mut path = Vec::new();
+
+        for _ in 0..entries {
+            let entry = index.get(offset..offset + 62)?;
+            let (mtime, mtime_nsec) = (be32(&entry[8..12]), be32(&entry[12..16]));
+            let mode = be32(&entry[24..28]);
+            let size = be32(&entry[36..40]);
+            let flags = be16(&entry[60..62]);
+
+            // Unmerged entries are always considered to be dirty.
+            if flags & 0x3000 != 0 {
+                return Some(true);
+            }
+
+            let mut header = 62;
+            let mut skip_worktree = false;
+            if version >= 3 && flags & 0x4000 != 0 {
+                skip_worktree = be16(index.get(offset + 62..offset + 64)?) & 0x4000 != 0;
+                header = 64;
+            }
+
+            let name = offset + header;
+            if version >= 4 {
+                // The path is compressed by stripping a number of bytes from the previous path.
+                let (strip, length) = varint(index.get(name..)?)?;
+                let start = name + length;
+                let end = start + index.get(start..)?.iter().position(|&b| b == 0)?;
+                path.truncate(path.len().checked_sub(strip as usize)?);
+                path.extend_from_slice(&index[start..end]);
+                offset = end + 1;
+            } else {
+                let end = name + index.get(name..)?.iter().position(|&b| b == 0)?;
+                path.clear();
+                path.extend_from_slice(&index[name..end]);
+                offset += (header + end - name + 8) & !7;
+            }
+
+            // Entries marked as assume-valid, skip-worktree entries, and submodules are skipped.
+            if flags & 0x8000 != 0 || skip_worktree || mode & 0o170_000 == 0o160_000 {
+                continue;
+            }
+
+            let file = self.work_tree.join(OsStr::from_bytes(&path));
+            let metadata = match fs::symlink_metadata(&file) {
+                Ok(metadata) => metadata,
+                Err(_) => return Some(true),
+            };
+
+            if metadata.size() as u32 != size {
+                return Some(true);
+            }
+
+            // A file which was only touched since it was staged still has the same contents, and
+            // so is compared against the staged blob once its modification time differs.
+            if (metadata.mtime() as u32 != mtime
+                || (mtime_nsec != 0 && metadata.mtime_nsec() as u32 != mtime_nsec))
+                && blob_id(&file, &metadata).as_ref().map(|id| &id[..]) != Some(&entry[40..60])
+            {
+                return Some(true);
+            }
+        }
+
+        Some(false)
+    }
+}
+
+enum Head {
+    Branch(String),
+    Detached(String),
+}
+
+/// The status of a repository, of which the more expensive fields are computed on demand.
+struct Status {
+    repository:   Repository,
+    head:         Head,
+    commit:       Option<String>,
+    upstream:     Option<(String, String)>,
+    ahead_behind: Cell<Option<Option<(usize, usize)>>>,
+    dirty:        Cell<Option<Option<bool>>>,
+}
+
+impl Status {
+    fn new(repository: Repository) -> Option<Status> {
+        let head = repository.read_ref("HEAD")?;
+        let (head, commit) = if head.starts_with("ref: ") {
+            let reference = &head[5..];
+            let commit = repository.resolve(reference);
+            let branch =
+                if reference.starts_with("refs/heads/") { &reference[11..] } else { reference };
+            (Head::Branch(branch.to_owned()), commit)
+        } else {
+            (Head::Detached(head.clone()), Some(head))
+        };
+
+        let upstream = match head {
+            Head::Branch(ref branch) => repository.upstream(branch),
+            Head::Detached(_) => None,
+        };
+
+        Some(Status {
+            repository,
+            head,
+            commit,
+            upstream,
+            ahead_behind: Cell::new(None),
+            dirty: Cell::new(None),
+        })
+    }
+
+    fn field(&self, field: &str) -> Option<String> {
+        match field {
+            "ahead" => self.ahead_behind().map(|(ahead, _)| ahead.to_string()),
+            "behind" => self.ahead_behind().map(|(_, behind)| behind.to_string()),
+            "branch" => match self.head {
+                Head::Branch(ref branch) => Some(branch.clone()),
+                Head::Detached(_) => None,
+            },
+            "commit" => self.short_commit(),
+            "dirty" => self.is_dirty().map(|dirty| if dirty { "1" } else { "0" }.to_owned()),
+            "head" => match self.head {
+                Head::Branch(ref branch) => Some(branch.clone()),
+                Head::Detached(_) => self.short_commit(),
+            },
+            "upstream" => self.upstream.as_ref().map(|(name, _)| name.clone()),
+            _ => None,
+        }
+    }
+
+    fn short_commit(&self) -> Option<String> {
+        self.commit.as_ref().and_then(|commit| commit.get(..7)).map(str::to_owned)
+    }
+
+    fn ahead_behind(&self) -> Option<(usize, usize)> {
+        if self.ahead_behind.get().is_none() {
+            let ahead_behind = match (&self.commit, &self.upstream) {
+                (Some(local), Some((_, upstream))) => {
+                    self.repository.ahead_behind(local, upstream)
+                }
+                _ => None,
+            };
+            self.ahead_behind.set(Some(ahead_behind));
+        }
+        self.ahead_behind.get().unwrap()
+    }
+
+    fn is_dirty(&self) -> Option<bool> {
+        if self.dirty.get().is_none() {
+            self.dirty.set(Some(self.repository.is_dirty()));
+        }
+        self.dirty.get().unwrap()
+    }
+}
+
+struct Commit {
+    time:    i64,
+    parents: Vec<String>,
+}
+
+const OBJ_COMMIT: u8 = 1;
+const OBJ_TREE: u8 = 2;
+const OBJ_BLOB: u8 = 3;
+const OBJ_TAG: u8 = 4;
+const OBJ_OFS_DELTA: u8 = 6;
+const OBJ_REF_DELTA: u8 = 7;
+
+/// Reads objects from the object database, whether they are loose or within a pack.
+struct Objects {
+    directory: PathBuf,
+    packs:     Vec<Pack>,
+}
+
+impl Objects {
+    fn new(directory: PathBuf) -> Objects {
+        let paths: Vec<PathBuf> = fs::read_dir(directory.join("pack"))
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension() == Some(OsStr::new("idx")))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Only the indexes of this repository are kept, so that the cache cannot grow as the
+        // shell moves between repositories.
+        let packs = PACK_INDEXES.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            let mut cached = HashMap::new();
+            let packs = paths
+                .into_iter()
+                .filter_map(|path| {
+                    let metadata = fs::metadata(&path).ok()?;
+                    let (modified, size) = (metadata.modified().ok()?, metadata.len());
+                    let index = match cache.remove(&path) {
+                        Some((time, length, index)) if time == modified && length == size => index,
+                        _ => Rc::new(fs::read(&path).ok()?),
+                    };
+                    cached.insert(path.clone(), (modified, size, index.clone()));
+                    Some(Pack { index, path: path.with_extension("pack") })
+                })
+                .collect();
+            *cache = cached;
+            packs
+        });
+
+        Objects { directory, packs }
+    }
+
+    fn commit(&self, id: &str) -> Option<Commit> {
+        let (kind, data) = self.read(id, 0)?;
+        if kind != OBJ_COMMIT {
+            return None;
+        }
+
+        let data = String::from_utf8_lossy(&data);
+        let mut commit = Commit { time: 0, parents: Vec::new() };
+        for line in data.lines().take_while(|line| !line.is_empty()) {
+            if line.starts_with("parent ") {
+                commit.parents.push(line[7..].to_owned());
+            } else if line.starts_with("committer ") {
+                // The time precedes the time zone at the end of the line.
+                commit.time = line.rsplitn(3, ' ').nth(1)?.parse().ok()?;
+            }
+        }
+
+        Some(commit)
+    }
+
+    fn read(&self, id: &str, depth: usize) -> Option<(u8, Vec<u8>)> {
+        let loose = self.directory.join(id.get(..2)?).join(id.get(2..)?);
+        if let Ok(compressed) = fs::read(loose) {
+            let data = decompress_to_vec_zlib(&compressed).ok()?;
+            let separator = data.iter().position(|&b| b == 0)?;
+            let kind = match data[..separator].split(|&b| b == b' ').next()? {
+                b"commit" => OBJ_COMMIT,
+                b"tree" => OBJ_TREE,
+                b"blob" => OBJ_BLOB,
+                b"tag" => OBJ_TAG,
+                _ => return None,
+            };
+            return Some((kind, data[separator + 1..].to_vec()));
+        }
+
+        let id = hex_to_bytes(id)?;
+        self.packs.iter().find_map(|pack| {
+            let offset = pack.find(&id)?;
+            self.read_packed(pack, offset, depth)
+        })
+    }
+
+    fn read_packed(&self, pack: &Pack, offset: u64, depth: usize) -> Option<(u8, Vec<u8>)> {
+        if depth > MAX_DELTA_DEPTH {
+            return None;
+        }
+
+        let mut file = File::open(&pack.path).ok()?;
+        let mut buffer = read_at(&mut file, offset, 4096)?;
+
+        // The header contains the type of the object, and the size of its data.
+        let mut byte = *buffer.first()?;
+        let kind = (byte >> 4) & 7;
+        let mut size = u64::from(byte & 15);
+        let mut shift = 4;
+        let mut position = 1;
+        while byte & 0x80 != 0 {
+            byte = *buffer.get(position)?;
+            size |= u64::from(byte & 0x7f).checked_shl(shift)?;
+            shift += 7;
+            position += 1;
+        }
+
+        let base = match kind {
+            OBJ_OFS_DELTA => {
+                let (distance, length) = varint(buffer.get(position..)?)?;
+                position += length;
+                Some(self.read_packed(pack, offset.checked_sub(distance)?, depth + 1)?)
+            }
+            OBJ_REF_DELTA => {
+                let id = bytes_to_hex(buffer.get(position..position + 20)?);
+                position += 20;
+                Some(self.read(&id, depth + 1)?)
+            }
+            _ => None,
+        };
+
+        // As the compressed size is unknown, more of the pack is read until it suffices.
+        let mut length = (size + 64).max(4096) + position as u64;
+        let data = loop {
+            if (buffer.len() as u64) < length {
+                buffer = read_at(&mut file, offset, length)?;
+            }
+
+            match decompress_to_vec_zlib(&buffer[position..]) {
+                Ok(data) => break data,
+                Err(_) if buffer.len() as u64 == length => length *= 2,
+                Err(_) => return None,
+            }
+        };
+
+        match base {
+            Some((kind, base)) => Some((kind, apply_delta(&base, &data)?)),
+            None => Some((kind, data)),
+        }
+    }
+}
+
+struct Pack {
+    /// The contents of the version 2 index of the pack.
+    index: Rc<Vec<u8>>,
+    path:  PathBuf,
+}
+
+impl Pack {
+    /// Finds the offset of the object within the pack.
+    fn find(&self, id: &[u8]) -> Option<u64> {
+        let index = &self.index;
+        if index.get(..8)? != b"\xfftOc\x00\x00\x00\x02" {
+            return None;
+        }
+
+        let fanout = |byte: usize| index.get(8 + byte * 4..12 + byte * 4).map(be32);
+        let total = fanout(255)? as usize;
+        let mut low = if id[0] == 0 { 0 } else { fanout(id[0] as usize - 1)? as usize };
+        let mut high = fanout(id[0] as usize)? as usize;
+
+        let ids = 8 + 256 * 4;
+        while low < high {
+            let middle = (low + high) / 2;
+            let candidate = index.get(ids + middle * 20..ids + middle * 20 + 20)?;
+            match candidate.cmp(id) {
+                std::cmp::Ordering::Less => low = middle + 1,
+                std::cmp::Ordering::Greater => high = middle,
+                std::cmp::Ordering::Equal => {
+                    let offsets = ids + total * 24;
+                    let offset = be32(index.get(offsets + middle * 4..offsets + middle * 4 + 4)?);
+                    if offset & 0x8000_0000 == 0 {
+                        return Some(u64::from(offset));
+                    }
+
+                    // Offsets beyond 2 GiB are stored within a separate table.
+                    let large = offsets + total * 4 + (offset & 0x7fff_ffff) as usize * 8;
+                    let large = index.get(large..large + 8)?;
+                    return Some(u64::from(be32(&large[..4])) << 32 | u64::from(be32(&large[4..])));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Reconstructs an object from its base and the instructions of a delta.
+fn apply_delta(base: &[u8], delta: &[u8]) -> Option<Vec<u8>> {
+    let size = |delta: &[u8], position: &mut usize| {
+        let (mut size, mut shift) = (0usize, 0);
+        loop {
+            let byte = *delta.get(*position)?;
+            *position += 1;
+            size |= ((byte & 0x7f) as usize).checked_shl(shift)?;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Some(size);
+            }
+        }
+    };
+
+    let mut position = 0;
+    let base_size = size(delta, &mut position)?;
+    let result_size = size(delta, &mut position)?;
+    if base_size != base.len() {
+        return None;
+    }
+
+    let mut result = Vec::with_capacity(result_size);
+    while position < delta.len() {
+        let instruction = delta[position];
+        position += 1;
+
+        if instruction & 0x80 != 0 {
+            // Copies a range of the base, of which the offset and size are given by the bytes
+            // that are flagged by the instruction.
+            let mut parameters = [0usize; 7];
+            for (bit, parameter) in parameters.iter_mut().enumerate() {
+                if instruction & (1 << bit) != 0 {
+                    *parameter = *delta.get(position)? as usize;
+                    position += 1;
+                }
+            }
+
+            let offset = parameters[0]
+                | parameters[1] << 8
+                | parameters[2] << 16
+                | parameters[3] << 24;
+            let length = match parameters[4] | parameters[5] << 8 | parameters[6] << 16 {
+                0 => 0x10000,
+                length => length,
+            };
+            result.extend_from_slice(base.get(offset..offset + length)?);
+        } else if instruction != 0 {
+            // Inserts the bytes that follow the instruction.
+            let length = instruction as usize;
+            result.extend_from_slice(delta.get(position..position + length)?);
+            position += length;
+        } else {
+            return None;
+        }
+    }
+
+    if result.len() == result_size {
+        Some(result)
+    } else {
+        None
+    }
+}
+
+fn read_at(file: &mut File, offset: u64, length: u64) -> Option<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    let mut buffer = Vec::with_capacity(length as usize);
+    file.take(length).read_to_end(&mut buffer).ok()?;
+    Some(buffer)
+}
+
+/// Decodes the variable-length integers of offset deltas and index paths, returning the value
+/// and the number of bytes that it occupied.
+fn varint(bytes: &[u8]) -> Option<(u64, usize)> {
+    let mut byte = *bytes.first()?;
+    let mut value = u64::from(byte & 0x7f);
+    let mut length = 1;
+    while byte & 0x80 != 0 {
+        byte = *bytes.get(length)?;
+        value = (value + 1).checked_shl(7)? | u64::from(byte & 0x7f);
+        length += 1;
+    }
+    Some((value, length))
+}
+
+fn be16(bytes: &[u8]) -> u16 { u16::from(bytes[0]) << 8 | u16::from(bytes[1]) }
+
+fn be32(bytes: &[u8]) -> u32 {
+    u32::from(bytes[0]) << 24 | u32::from(bytes[1]) << 16 | u32::from(bytes[2]) << 8
+        | u32::from(bytes[3])
+}
+
+/// Computes the ID which the file would have as a blob: the hash of its contents, or of the
+/// target of a symbolic link.
+fn blob_id(file: &Path, metadata: &fs::Metadata) -> Option<[u8; 20]> {
+    let contents = if metadata.file_type().is_symlink() {
+        fs::read_link(file).ok()?.into_os_string().into_vec()
+    } else {
+        fs::read(file).ok()?
+    };
+
+    let mut blob = format!("blob {}\0", contents.len()).into_bytes();
+    blob.extend_from_slice(&contents);
+    Some(sha1(&blob))
+}
+
+/// Hashes the data with SHA-1, which git uses to identify objects.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476, 0xc3d2_e1f0];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    let bits = (data.len() as u64).wrapping_mul(8);
+    message.extend((0..8).rev().map(|byte| (bits >> (byte * 8)) as u8));
+
+    for block in message.chunks(64) {
+        let mut words = [0u32; 80];
+        for (index, word) in block.chunks(4).enumerate() {
+            words[index] = be32(word);
+        }
+        for index in 16..80 {
+            words[index] = (words[index - 3] ^ words[index - 8] ^ words[index - 14]
+                ^ words[index - 16])
+                .rotate_left(1);
+        }
+
+        let (mut a, mut b, mut c, mut d, mut e) = (state[0], state[1], state[2], state[3], state[4]);
+        for (index, &word) in words.iter().enumerate() {
+            let (f, k) = match index {
+                0..=19 => ((b & c) | (!b & d), 0x5a82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ed9_eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+                _ => (b ^ c ^ d, 0xca62_c1d6u32),
+            };
+            let temp =
+                a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (value, add) in state.iter_mut().zip(&[a, b, c, d, e]) {
+            *value = value.wrapping_add(*add);
+        }
+    }
+
+    let mut hash = [0; 20];
+    for (index, value) in state.iter().enumerate() {
+        hash[index * 4..index * 4 + 4].copy_from_slice(&[
+            (value >> 24) as u8,
+            (value >> 16) as u8,
+            (value >> 8) as u8,
+            *value as u8,
+        ]);
+    }
+    hash
+}
+
+fn hex_to_bytes(id: &str) -> Option<Vec<u8>> {
+    if id.len() != 40 {
+        return None;
+    }
+
+    (0..40).step_by(2).map(|index| u8::from_str_radix(id.get(index..index + 2)?, 16).ok()).collect()
+}
+
+fn bytes_to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniz_oxide::deflate::compress_to_vec_zlib;
+    use std::process::{self, Command, Stdio};
+
+    const BASE: &str = "1111111111111111111111111111111111111111";
+    const LOCAL_1: &str = "2222222222222222222222222222222222222222";
+    const LOCAL_2: &str = "3333333333333333333333333333333333333333";
+    const REMOTE_1: &str = "4444444444444444444444444444444444444444";
+
+    /// A repository within a temporary directory, which is removed when dropped.
+    struct TempRepository(PathBuf);
+
+    impl TempRepository {
+        fn new(name: &str) -> TempRepository {
+            let repo = TempRepository::empty(name);
+            fs::create_dir_all(repo.0.join(".git/refs/heads")).unwrap();
+            fs::create_dir_all(repo.0.join(".git/objects")).unwrap();
+            repo
+        }
+
+        /// Creates an empty directory, in which repositories are created by `git` itself.
+        fn empty(name: &str) -> TempRepository {
+            let root = env::temp_dir().join(format!("ion-git-{}-{}", process::id(), name));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            TempRepository(root)
+        }
+
+        fn write(&self, path: &str, contents: &[u8]) {
+            let path = self.0.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        fn commit(&self, id: &str, parents: &[&str], time: i64) {
+            let mut body = String::from("tree 0000000000000000000000000000000000000000\n");
+            for parent in parents {
+                body.push_str(&format!("parent {}\n", parent));
+            }
+            body.push_str(&format!("author A <a@b> {} +0000\n", time));
+            body.push_str(&format!("committer A <a@b> {} +0000\n\nmessage\n", time));
+
+            let object = format!("commit {}\0{}", body.len(), body);
+            let path = format!(".git/objects/{}/{}", &id[..2], &id[2..]);
+            self.write(&path, &compress_to_vec_zlib(object.as_bytes(), 6));
+        }
+
+        fn status(&self) -> Status {
+            Status::new(Repository::discover(&self.0.join("src")).unwrap()).unwrap()
+        }
+    }
+
+    impl Drop for TempRepository {
+        fn drop(&mut self) { let _ = fs::remove_dir_all(&self.0); }
+    }
+
+    /// Whether `git` is installed, without which the repositories created by it are not tested.
+    fn git_available() -> bool {
+        Command::new("git")
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false)
+    }
+
+    /// Executes `git` within the directory, with the commit dates set to `time`, as the
+    /// divergence is computed by walking the commits from the newest to the oldest.
+    fn git(directory: &Path, time: i64, args: &[&str]) {
+        let date = format!("@{} +0000", time);
+        let status = Command::new("git")
+            .args(&["-c", "user.name=ion", "-c", "user.email=ion@localhost"])
+            .args(&["-c", "commit.gpgsign=false"])
+            .args(args)
+            .current_dir(directory)
+            .env("GIT_AUTHOR_DATE", &date)
+            .env("GIT_COMMITTER_DATE", &date)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .expect("unable to execute git");
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn branch_from_loose_ref() {
+        let repo = TempRepository::new("loose");
+        repo.write(".git/HEAD", b"ref: refs/heads/master\n");
+        repo.write(".git/refs/heads/master", format!("{}\n", BASE).as_bytes());
+        fs::create_dir_all(repo.0.join("src")).unwrap();
+
+        let status = repo.status();
+        assert_eq!(status.field("branch"), Some("master".into()));
+        assert_eq!(status.field("head"), Some("master".into()));
+        assert_eq!(status.field("commit"), Some("1111111".into()));
+        assert_eq!(status.field("upstream"), None);
+    }
+
+    #[test]
+    fn detached_head() {
+        let repo = TempRepository::new("detached");
+        repo.write(".git/HEAD", format!("{}\n", LOCAL_1).as_bytes());
+        fs::create_dir_all(repo.0.join("src")).unwrap();
+
+        let status = repo.status();
+        assert_eq!(status.field("branch"), None);
+        assert_eq!(status.field("head"), Some("2222222".into()));
+        assert_eq!(status.field("commit"), Some("2222222".into()));
+    }
+
+    #[test]
+    fn upstream_from_packed_refs() {
+        let repo = TempRepository::new("packed");
+        repo.write(".git/HEAD", b"ref: refs/heads/master\n");
+        repo.write(
+            ".git/packed-refs",
+            format!(
+                "# pack-refs with: peeled fully-peeled sorted\n{} refs/heads/master\n{} \
+                 refs/remotes/origin/master\n",
+                LOCAL_2, REMOTE_1
+            )
+            .as_bytes(),
+        );
+        repo.write(
+            ".git/config",
+            b"[core]\n\tbare = false\n[branch \"master\"]\n\tremote = origin\n\tmerge = \
+              refs/heads/master\n",
+        );
+        repo.commit(BASE, &[], 100);
+        repo.commit(LOCAL_1, &[BASE], 200);
+        repo.commit(LOCAL_2, &[LOCAL_1], 300);
+        repo.commit(REMOTE_1, &[BASE], 250);
+        fs::create_dir_all(repo.0.join("src")).unwrap();
+
+        let status = repo.status();
+        assert_eq!(status.field("commit"), Some("3333333".into()));
+        assert_eq!(status.field("upstream"), Some("origin/master".into()));
+        assert_eq!(status.field("ahead"), Some("2".into()));
+        assert_eq!(status.field("behind"), Some("1".into()));
+    }
+
+    #[test]
+    fn dirty_work_tree() {
+        let repo = TempRepository::new("dirty");
+        repo.write(".git/HEAD", b"ref: refs/heads/master\n");
+        repo.write("src/file", b"contents");
+
+        // Creates an index with a single entry, matching the file except for its modification
+        // time, as if it were touched since it was staged.
+        let metadata = fs::metadata(repo.0.join("src/file")).unwrap();
+        let name = b"src/file";
+        let mut index = b"DIRC\x00\x00\x00\x02\x00\x00\x00\x01".to_vec();
+        let mut entry = Vec::new();
+        for value in &[
+            metadata.ctime() as u32,
+            metadata.ctime_nsec() as u32,
+            metadata.mtime() as u32 - 1,
+            metadata.mtime_nsec() as u32,
+            metadata.dev() as u32,
+            metadata.ino() as u32,
+            0o100_644,
+            metadata.uid(),
+            metadata.gid(),
+            metadata.size() as u32,
+        ] {
+            entry.extend_from_slice(&[
+                (value >> 24) as u8,
+                (value >> 16) as u8,
+                (value >> 8) as u8,
+                *value as u8,
+            ]);
+        }
+        entry.extend_from_slice(&sha1(b"blob 8\0contents"));
+        entry.extend_from_slice(&[(name.len() >> 8) as u8, name.len() as u8]);
+        entry.extend_from_slice(name);
+        let padded = (entry.len() + 8) & !7;
+        entry.resize(padded, 0);
+        index.extend_from_slice(&entry);
+        repo.write(".git/index", &index);
+
+        assert_eq!(repo.status().field("dirty"), Some("0".into()));
+
+        repo.write("src/file", b"CONTENTS");
+        assert_eq!(repo.status().field("dirty"), Some("1".into()));
+
+        repo.write("src/file", b"modified contents");
+        assert_eq!(repo.status().field("dirty"), Some("1".into()));
+
+        fs::remove_file(repo.0.join("src/file")).unwrap();
+        assert_eq!(repo.status().field("dirty"), Some("1".into()));
+    }
+
+    #[test]
+    fn blob_ids() {
+        let empty = sha1(b"blob 0\0");
+        assert_eq!(hex_to_bytes("e69de29bb2d1d6434b8b29ae775ad8c2e48c5391"), Some(empty.to_vec()));
+        let abc = sha1(b"abc");
+        assert_eq!(hex_to_bytes("a9993e364706816aba3e25717850c26c9cd0d89d"), Some(abc.to_vec()));
+    }
+
+    #[test]
+    fn deltas() {
+        // Copies "hello " from the base, then inserts "ion".
+        let delta = [11, 9, 0x90, 6, 3, b'i', b'o', b'n'];
+        assert_eq!(apply_delta(b"hello world", &delta), Some(b"hello ion".to_vec()));
+        assert_eq!(varint(&[0x80, 0x00]), Some((128, 2)));
+        assert_eq!(varint(&[0x05]), Some((5, 1)));
+    }
+
+    #[test]
+    fn repository_packed_by_git() {
+        if !git_available() {
+            eprintln!("skipping repository_packed_by_git: git is not installed");
+            return;
+        }
+
+        let repo = TempRepository::empty("packed-by-git");
+        let upstream = repo.0.join("upstream");
+        let local = repo.0.join("local");
+        fs::create_dir_all(upstream.join("src")).unwrap();
+
+        git(&upstream, 100, &["init", "-q"]);
+        git(&upstream, 100, &["symbolic-ref", "HEAD", "refs/heads/master"]);
+        fs::write(upstream.join("src/file"), "base\n").unwrap();
+        git(&upstream, 100, &["add", "."]);
+        git(&upstream, 100, &["commit", "-q", "-m", "base"]);
+        git(&repo.0, 100, &["clone", "-q", "upstream", "local"]);
+
+        for &(time, contents) in &[(200, "local 1\n"), (300, "local 2\n")] {
+            fs::write(local.join("src/file"), contents).unwrap();
+            git(&local, time, &["commit", "-q", "-a", "-m", contents]);
+        }
+        fs::write(upstream.join("src/other"), "remote\n").unwrap();
+        git(&upstream, 250, &["add", "."]);
+        git(&upstream, 250, &["commit", "-q", "-m", "remote"]);
+        git(&local, 400, &["fetch", "-q"]);
+        git(&local, 400, &["gc", "-q"]);
+
+        // Every commit and ref is read from the pack and packed-refs files.
+        let objects = local.join(".git/objects");
+        for entry in fs::read_dir(&objects).unwrap() {
+            let path = entry.unwrap().path();
+            if path.file_name().map_or(false, |name| name.len() == 2) {
+                assert_eq!(fs::read_dir(&path).unwrap().count(), 0, "{:?} is loose", path);
+            }
+        }
+        assert!(!local.join(".git/refs/heads/master").exists());
+
+        let status = Status::new(Repository::discover(&local.join("src")).unwrap()).unwrap();
+        assert_eq!(status.field("branch"), Some("master".into()));
+        assert_eq!(status.field("upstream"), Some("origin/master".into()));
+        assert_eq!(status.field("ahead"), Some("2".into()));
+        assert_eq!(status.field("behind"), Some("1".into()));
+        assert_eq!(status.field("dirty"), Some("0".into()));
+
+        // Rewriting a file with the same contents does not make it dirty.
+        fs::write(local.join("src/file"), "local 2\n").unwrap();
+        let status = Status::new(Repository::discover(&local.join("src")).unwrap()).unwrap();
+        assert_eq!(status.field("dirty"), Some("0".into()));
+
+        fs::write(local.join("src/file"), "modified contents\n").unwrap();
+        let status = Status::new(Repository::discover(&local.join("src")).unwrap()).unwrap();
+        assert_eq!(status.field("dirty"), Some("1".into()));
+    }
+}
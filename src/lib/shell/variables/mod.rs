@@ -1,3 +1,4 @@
+pub(crate) mod git;
 mod math;
 mod modification;
 
@@ -5,22 +6,14 @@ pub use self::{
     math::{EuclDiv, OpError, Pow},
     modification::Modifications,
 };
-use super::{
-    colors::Colors,
-    directory_stack::DirectoryStack,
-    flow_control::Function,
-    status::{FAILURE, SUCCESS},
-};
+use super::{colors::Colors, directory_stack::DirectoryStack, flow_control::Function};
 use crate::{
-    sys::{self, env as sys_env, geteuid, getpid, getuid, variables as self_sys},
+    sys::{env as sys_env, geteuid, getpid, getuid, variables as self_sys},
     types::{self, Array},
 };
 use hashbrown::HashMap;
-use liner::Context;
 use std::{
-    env, fmt,
-    io::{self, BufRead},
-    mem,
+    env, fmt, mem,
     ops::{Deref, DerefMut},
 };
 use unicode_segmentation::UnicodeSegmentation;
@@ -599,33 +592,6 @@ impl Variables {
             })
             .flat_map(|f| f)
     }
-
-    pub(crate) fn read<I: IntoIterator>(&mut self, args: I) -> i32
-    where
-        I::Item: AsRef<str>,
-    {
-        if sys::isatty(sys::STDIN_FILENO) {
-            let mut con = Context::new();
-            for arg in args.into_iter().skip(1) {
-                match con.read_line(format!("{}=", arg.as_ref().trim()), None, &mut |_| {}) {
-                    Ok(buffer) => {
-                        self.set(arg.as_ref(), buffer.trim());
-                    }
-                    Err(_) => return FAILURE,
-                }
-            }
-        } else {
-            let stdin = io::stdin();
-            let handle = stdin.lock();
-            let mut lines = handle.lines();
-            for arg in args.into_iter().skip(1) {
-                if let Some(Ok(line)) = lines.next() {
-                    self.set(arg.as_ref(), line.trim());
-                }
-            }
-        }
-        SUCCESS
-    }
 }
 
 pub trait GetVariable<T> {
@@ -659,6 +625,7 @@ impl GetVariable<types::Str> for Variables {
             Some(("env", variable)) => {
                 env::var(variable).map(Into::into).ok().map(|s| Str::from(Value::Str(s)))
             }
+            Some(("git", variable)) => git::get(variable).map(|s| Str::from(Value::Str(s.into()))),
             Some(("super", _)) | Some(("global", _)) | None => {
                 // Otherwise, it's just a simple variable name.
                 match self.get_ref(name) {